@@ -0,0 +1,48 @@
+use std::path::PathBuf;
+
+use clap::{Args, Subcommand};
+
+use crate::{
+    service::{
+        config::{Config, Manifest},
+        module::{add_module, Module},
+    },
+    util::{error::Error, file::current_dir},
+};
+
+/// Add code to the current project
+#[derive(Debug, Args)]
+pub struct Command {
+    #[clap(subcommand)]
+    pub item: Item,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Item {
+    /// Add a source file and header to `src`, with a unit test file
+    Module {
+        /// Path of the module within `src`, such as `util/str`
+        path: PathBuf,
+    },
+}
+
+impl Command {
+    #[tracing::instrument(level = "debug")]
+    pub fn run(&self) -> anyhow::Result<()> {
+        let (root, config) = current_package()?;
+
+        match &self.item {
+            Item::Module { path } => add_module(&root, &config, &Module::new(path)?),
+        }
+    }
+}
+
+/// Root and config of the package containing the current directory
+pub fn current_package() -> anyhow::Result<(PathBuf, Config)> {
+    let mut root = Manifest::find_root(&current_dir()?)?;
+
+    match Manifest::load_from_dir(&mut root)? {
+        Manifest::Package(config) => Ok((root, *config)),
+        Manifest::Workspace(_) => anyhow::bail!(Error::NotPackage(root)),
+    }
+}
@@ -0,0 +1,51 @@
+use std::num::NonZeroUsize;
+
+use clap::Args;
+
+use crate::{
+    service::{
+        build::{build_workspace, BuildOptions},
+        profile::Profile,
+        workspace::Workspace,
+    },
+    util::file::current_dir,
+};
+
+/// Compile the current project
+#[derive(Debug, Args)]
+pub struct Command {
+    #[clap(flatten)]
+    pub build: BuildArgs,
+}
+
+/// Options shared by commands which compile the project
+#[derive(Debug, Args)]
+pub struct BuildArgs {
+    /// Number of files to compile in parallel, defaults to the number of CPUs
+    #[clap(long, short)]
+    pub jobs: Option<NonZeroUsize>,
+
+    /// Build with the release profile, into `target/release`
+    #[clap(long)]
+    pub release: bool,
+}
+
+impl BuildArgs {
+    pub fn options(&self) -> BuildOptions {
+        BuildOptions {
+            jobs: self.jobs,
+            profile: Profile::from_release_flag(self.release),
+        }
+    }
+}
+
+impl Command {
+    #[tracing::instrument(level = "debug")]
+    pub fn run(&self) -> anyhow::Result<()> {
+        let workspace = Workspace::find(&current_dir()?)?;
+
+        build_workspace(&workspace, &self.build.options())?;
+
+        Ok(())
+    }
+}
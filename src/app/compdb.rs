@@ -0,0 +1,34 @@
+use clap::Args;
+
+use crate::{
+    service::{build::BuildOptions, compdb, profile::Profile, workspace::Workspace},
+    util::file::current_dir,
+};
+
+/// Write compile_commands.json for the current project without building
+#[derive(Debug, Args)]
+pub struct Command {
+    /// Use the flags of the release profile
+    #[clap(long)]
+    pub release: bool,
+}
+
+impl Command {
+    #[tracing::instrument(level = "debug")]
+    pub fn run(&self) -> anyhow::Result<()> {
+        let workspace = Workspace::find(&current_dir()?)?;
+        let options = BuildOptions {
+            profile: Profile::from_release_flag(self.release),
+            ..BuildOptions::default()
+        };
+
+        for package in workspace.members() {
+            let context = workspace.context(package, &options);
+            compdb::update(&context, &package.config)?;
+        }
+
+        tracing::info!("Updated `compile_commands.json`");
+
+        Ok(())
+    }
+}
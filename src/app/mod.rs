@@ -1,7 +1,17 @@
 use clap::{Parser, Subcommand};
 
+use crate::util::message::MessageFormat;
+
+mod add;
+mod build;
+mod compdb;
 mod init;
 mod new;
+mod rename;
+mod rm;
+mod run;
+mod test;
+mod watch;
 
 #[derive(Debug, Parser)]
 #[clap(author, version, about, long_about = None)]
@@ -12,12 +22,25 @@ pub struct Args {
     /// Turn on logging output
     #[clap(short, long)]
     pub verbose: bool,
+
+    /// Format of progress messages, `json` prints one JSON object per line to
+    /// stdout
+    #[clap(long, global = true, value_enum, default_value_t = MessageFormat::default())]
+    pub message_format: MessageFormat,
 }
 
 #[derive(Debug, Subcommand)]
 pub enum Command {
     New(new::Command),
     Init(init::Command),
+    Build(build::Command),
+    Run(run::Command),
+    Test(test::Command),
+    Compdb(compdb::Command),
+    Watch(watch::Command),
+    Add(add::Command),
+    Rename(rename::Command),
+    Rm(rm::Command),
 }
 
 impl Args {
@@ -25,6 +48,14 @@ impl Args {
         match &self.command {
             Command::New(com) => com.run(),
             Command::Init(com) => com.run(),
+            Command::Build(com) => com.run(),
+            Command::Run(com) => com.run(),
+            Command::Test(com) => com.run(),
+            Command::Compdb(com) => com.run(),
+            Command::Watch(com) => com.run(),
+            Command::Add(com) => com.run(),
+            Command::Rename(com) => com.run(),
+            Command::Rm(com) => com.run(),
         }
     }
 }
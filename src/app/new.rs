@@ -3,7 +3,12 @@ use std::path::PathBuf;
 use clap::Args;
 
 use crate::{
-    service::{build_system::BuildSystem, init::project_init, vcs::VersionControl},
+    service::{
+        build_system::BuildSystem,
+        init::{project_init, ProjectKind},
+        template::Template,
+        vcs::VersionControl,
+    },
     util::{
         error::Error,
         file::{absolute_path, create_project_dir, normalize_path},
@@ -23,6 +28,17 @@ pub struct Command {
     /// Version control system
     #[clap(long, value_enum, default_value_t = VersionControl::default())]
     pub vcs: VersionControl,
+
+    /// Create a library instead of an executable
+    #[clap(long)]
+    pub lib: bool,
+
+    /// Copy the files of a template instead of creating a hello world: a
+    /// directory, a git repository or URL, or the name of a template in
+    /// `crame/templates` of the user config directory, such as
+    /// `~/.config/crame/templates`
+    #[clap(long)]
+    pub template: Option<String>,
 }
 
 impl Command {
@@ -36,10 +52,18 @@ impl Command {
             anyhow::bail!(Error::Conflict(path));
         }
 
+        let template = self.template.as_deref().map(Template::load).transpose()?;
+
         create_project_dir(&path)?;
 
         let path = normalize_path(&path)?;
 
-        project_init(path, self.build_system, self.vcs)
+        project_init(
+            path,
+            self.build_system,
+            self.vcs,
+            ProjectKind::from_lib_flag(self.lib),
+            template.as_ref(),
+        )
     }
 }
@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+
+use clap::{Args, Subcommand};
+
+use super::add::current_package;
+use crate::service::module::{rename_module, Module};
+
+/// Rename code in the current project, updating the files that include it
+#[derive(Debug, Args)]
+pub struct Command {
+    #[clap(subcommand)]
+    pub item: Item,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Item {
+    /// Move a module's source, header and unit test file, renaming its
+    /// include guard
+    Module {
+        /// Current path of the module within `src`
+        from: PathBuf,
+
+        /// New path of the module within `src`
+        to: PathBuf,
+    },
+}
+
+impl Command {
+    #[tracing::instrument(level = "debug")]
+    pub fn run(&self) -> anyhow::Result<()> {
+        let (root, config) = current_package()?;
+
+        match &self.item {
+            Item::Module { from, to } => rename_module(
+                &root,
+                &config.package.name,
+                &Module::new(from)?,
+                &Module::new(to)?,
+            ),
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use std::path::PathBuf;
+
+use clap::{Args, Subcommand};
+
+use super::add::current_package;
+use crate::service::module::{remove_module, Module};
+
+/// Remove code from the current project
+#[derive(Debug, Args)]
+pub struct Command {
+    #[clap(subcommand)]
+    pub item: Item,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Item {
+    /// Delete a module's source, header and unit test file, and the includes
+    /// of them
+    Module {
+        /// Path of the module within `src`
+        path: PathBuf,
+    },
+}
+
+impl Command {
+    #[tracing::instrument(level = "debug")]
+    pub fn run(&self) -> anyhow::Result<()> {
+        let (root, _) = current_package()?;
+
+        match &self.item {
+            Item::Module { path } => remove_module(&root, &Module::new(path)?),
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use std::ffi::OsString;
+
+use clap::Args;
+
+use super::build::BuildArgs;
+use crate::{
+    service::{
+        run::{exit_code, run_project},
+        workspace::Workspace,
+    },
+    util::file::current_dir,
+};
+
+/// Build and run the current project
+#[derive(Debug, Args)]
+pub struct Command {
+    #[clap(flatten)]
+    pub build: BuildArgs,
+
+    /// Name of the executable to run
+    #[clap(long)]
+    pub bin: Option<String>,
+
+    /// Arguments passed to the executable
+    #[clap(last = true)]
+    pub args: Vec<OsString>,
+}
+
+impl Command {
+    #[tracing::instrument(level = "debug")]
+    pub fn run(&self) -> anyhow::Result<()> {
+        let workspace = Workspace::find(&current_dir()?)?;
+
+        let status = run_project(
+            &workspace,
+            &self.build.options(),
+            self.bin.as_deref(),
+            &self.args,
+        )?;
+
+        if !status.success() {
+            tracing::debug!(?status, "Executable failed");
+            std::process::exit(exit_code(&status));
+        }
+
+        Ok(())
+    }
+}
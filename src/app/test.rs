@@ -0,0 +1,70 @@
+use std::path::PathBuf;
+
+use clap::Args;
+
+use super::build::BuildArgs;
+use crate::{
+    service::{
+        harness::TestFilter,
+        report::{Report, ReportFormat},
+        test::{list_tests, test_project},
+        workspace::Workspace,
+    },
+    util::file::current_dir,
+};
+
+/// Build and run the tests of the current project
+#[derive(Debug, Args)]
+pub struct Command {
+    /// Only run tests whose name or file path contains this pattern
+    #[clap(value_name = "PATTERN")]
+    pub filter: Option<String>,
+
+    /// Match the whole test name or file path, instead of any part of it
+    #[clap(long)]
+    pub exact: bool,
+
+    /// Skip tests whose name or file path contains this pattern, may be
+    /// given more than once
+    #[clap(long, value_name = "PATTERN")]
+    pub skip: Vec<String>,
+
+    /// List the selected tests without running them
+    #[clap(long)]
+    pub list: bool,
+
+    /// Write a report of the test results in this format
+    #[clap(long, value_enum)]
+    pub format: Option<ReportFormat>,
+
+    /// Write the report to a file instead of stdout
+    #[clap(long, requires = "format")]
+    pub output: Option<PathBuf>,
+
+    #[clap(flatten)]
+    pub build: BuildArgs,
+}
+
+impl Command {
+    #[tracing::instrument(level = "debug")]
+    pub fn run(&self) -> anyhow::Result<()> {
+        let workspace = Workspace::find(&current_dir()?)?;
+        let filter = TestFilter {
+            pattern: self.filter.clone(),
+            exact: self.exact,
+            skip: self.skip.clone(),
+        };
+
+        if self.list {
+            list_tests(&workspace, &self.build.options(), &filter)?;
+        } else {
+            let report = self.format.map(|format| Report {
+                format,
+                output: self.output.clone(),
+            });
+            test_project(&workspace, &self.build.options(), &filter, report.as_ref())?;
+        }
+
+        Ok(())
+    }
+}
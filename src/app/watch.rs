@@ -0,0 +1,46 @@
+use std::ffi::OsString;
+
+use clap::Args;
+
+use super::build::BuildArgs;
+use crate::{
+    service::{
+        watch::{watch, WatchAction},
+        workspace::Workspace,
+    },
+    util::file::current_dir,
+};
+
+/// Rebuild and rerun the tests or executable whenever the project changes
+#[derive(Debug, Args)]
+pub struct Command {
+    /// Command to rerun
+    #[clap(value_enum, default_value_t = WatchAction::default())]
+    pub action: WatchAction,
+
+    #[clap(flatten)]
+    pub build: BuildArgs,
+
+    /// Name of the executable to run
+    #[clap(long)]
+    pub bin: Option<String>,
+
+    /// Arguments passed to the executable
+    #[clap(last = true)]
+    pub args: Vec<OsString>,
+}
+
+impl Command {
+    #[tracing::instrument(level = "debug")]
+    pub fn run(&self) -> anyhow::Result<()> {
+        let workspace = Workspace::find(&current_dir()?)?;
+
+        watch(
+            &workspace,
+            &self.build.options(),
+            self.action,
+            self.bin.as_deref(),
+            &self.args,
+        )
+    }
+}
@@ -10,6 +10,7 @@ mod util;
 
 fn main() {
     let args = Args::parse();
+    util::message::set_format(args.message_format);
     setup_logging(&args);
     tracing::debug!("Starting app");
 
@@ -30,6 +31,7 @@ fn setup_logging(args: &Args) {
 
 fn std_logging() {
     fmt()
+        .with_writer(std::io::stderr)
         .with_max_level(Level::INFO)
         .with_target(false)
         .without_time()
@@ -37,5 +39,9 @@ fn std_logging() {
 }
 
 fn verbose_logging() {
-    fmt().pretty().with_max_level(Level::DEBUG).init();
+    fmt()
+        .pretty()
+        .with_writer(std::io::stderr)
+        .with_max_level(Level::DEBUG)
+        .init();
 }
@@ -0,0 +1,856 @@
+use super::{
+    compdb,
+    compiler::{Compiler, Invocation},
+    config::{Config, LibType},
+    fingerprint,
+    profile::Profile,
+    workspace::Workspace,
+};
+use crate::util::{
+    error::Error,
+    file::{find_files, lexical_normalize},
+    message, parallel,
+};
+
+use anyhow::Context;
+use serde::Serialize;
+
+use std::{
+    collections::BTreeMap,
+    env::consts::{DLL_PREFIX, DLL_SUFFIX, EXE_SUFFIX},
+    ffi::OsString,
+    fs,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+};
+
+pub const TARGET_DIR: &str = "target";
+pub const MAIN_FILE: &str = "src/main.c";
+pub const BIN_DIR: &str = "src/bin";
+/// Source containing the `main` of an executable in a directory of `src/bin`
+const BIN_DIR_MAIN: &str = "main.c";
+
+const SOURCE_DIRS: &[&str] = &["src", "lib"];
+const OBJECT_DIR: &str = "obj";
+
+/// Build settings given on the command line, taking precedence over config
+#[derive(Debug, Default, Clone)]
+pub struct BuildOptions {
+    pub jobs: Option<NonZeroUsize>,
+    pub profile: Profile,
+}
+
+impl BuildOptions {
+    /// Number of files to compile in parallel
+    pub fn jobs(&self, config: &Config) -> NonZeroUsize {
+        self.jobs
+            .or(config.build.jobs)
+            .unwrap_or_else(parallel::default_jobs)
+    }
+}
+
+/// Project location and settings shared by each step of a build
+#[derive(Debug)]
+pub struct BuildContext {
+    /// Project root, used as the working directory of the compiler
+    pub root: PathBuf,
+    /// Output directory of the profile, relative to the root
+    pub out_dir: PathBuf,
+    pub profile: Profile,
+    pub compiler: Compiler,
+    pub jobs: NonZeroUsize,
+    /// Package name, if the output directory is shared with other packages
+    pub package: Option<String>,
+    /// Libraries of dependencies, linked into each executable
+    pub libraries: Vec<PathBuf>,
+}
+
+/// Executable and the source containing its `main`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinTarget {
+    pub name: String,
+    pub main: PathBuf,
+    /// Other sources only compiled into this executable, from its directory
+    /// in `src/bin`
+    pub sources: Vec<PathBuf>,
+}
+
+/// Object files compiled from a set of sources
+#[derive(Debug, Default, Clone)]
+pub struct Objects {
+    pub paths: Vec<PathBuf>,
+    /// Whether any object was recompiled
+    pub rebuilt: bool,
+}
+
+impl Objects {
+    pub fn extend(&mut self, other: Objects) {
+        self.paths.extend(other.paths);
+        self.rebuilt |= other.rebuilt;
+    }
+}
+
+/// Files produced by a build, relative to the project root
+#[derive(Debug, Default)]
+pub struct Artifacts {
+    /// Executables by target name
+    pub executables: BTreeMap<String, PathBuf>,
+    pub libraries: Vec<PathBuf>,
+}
+
+/// Kind of file produced by a build
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArtifactKind {
+    Executable,
+    StaticLib,
+    SharedLib,
+    /// Test executable
+    Test,
+}
+
+impl From<LibType> for ArtifactKind {
+    fn from(lib_type: LibType) -> Self {
+        match lib_type {
+            LibType::Static => Self::StaticLib,
+            LibType::Shared => Self::SharedLib,
+        }
+    }
+}
+
+/// Artifact printed with `--message-format json`
+#[derive(Debug, Serialize)]
+#[serde(tag = "reason", rename = "compiler-artifact")]
+struct ArtifactMessage<'a> {
+    package: &'a str,
+    target: &'a str,
+    kind: ArtifactKind,
+    /// Absolute path of the file
+    path: PathBuf,
+}
+
+/// Report an artifact built for the `target` of `package` at `path`,
+/// relative to the context's root
+pub fn emit_artifact(
+    context: &BuildContext,
+    package: &str,
+    target: &str,
+    kind: ArtifactKind,
+    path: &Path,
+) {
+    message::emit(&ArtifactMessage {
+        package,
+        target,
+        kind,
+        path: lexical_normalize(&context.root.join(path)),
+    });
+}
+
+/// Build every package of the workspace, in dependency order
+pub fn build_workspace(workspace: &Workspace, options: &BuildOptions) -> anyhow::Result<Artifacts> {
+    check_bin_names(workspace)?;
+    let mut artifacts = Artifacts::default();
+
+    for package in &workspace.packages {
+        let context = workspace.context(package, options);
+        let package_artifacts = build_package(&context, &package.config)?;
+
+        artifacts.executables.extend(package_artifacts.executables);
+        artifacts.libraries.extend(package_artifacts.libraries);
+    }
+
+    Ok(artifacts)
+}
+
+/// Check that no two packages of the workspace have an executable with the
+/// same name, as they would be built to the same path
+pub fn check_bin_names(workspace: &Workspace) -> anyhow::Result<()> {
+    let mut names = BTreeMap::new();
+
+    for package in &workspace.packages {
+        let package_name = &package.config.package.name;
+
+        for target in bin_targets(&package.root, &package.config)? {
+            if let Some(other) = names.insert(target.name.clone(), package_name) {
+                anyhow::bail!(Error::DuplicateBin(
+                    target.name,
+                    other.clone(),
+                    package_name.clone()
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Build the package targets into the profile's output directory
+///
+/// An executable is built for each of the [`bin_targets`], and libraries are
+/// built if the config has a `[lib]` table. Every other source is compiled
+/// into all of them.
+#[tracing::instrument(level = "debug", skip_all, fields(root = ?context.root))]
+pub fn build_package(context: &BuildContext, config: &Config) -> anyhow::Result<Artifacts> {
+    let path = context.root.as_path();
+    let name = &config.package.name;
+    tracing::info!("Compiling `{}` [{}]", name, context.profile.dir_name());
+
+    let targets = bin_targets(path, config)?;
+    let sources = shared_sources(path, &targets)?;
+    warn_unbuilt_bin_dirs(path)?;
+
+    context.create_out_dir()?;
+    compdb::update(context, config)?;
+
+    let objects = context.compile_objects(&context.compiler, &sources, name)?;
+    let mut artifacts = Artifacts::default();
+
+    if let Some(lib) = &config.lib {
+        let lib_name = lib.name.as_deref().unwrap_or(name);
+
+        for &lib_type in &lib.types {
+            let output = context.library_path(lib_name, lib_type);
+            let invocation = match lib_type {
+                LibType::Static => context.compiler.archive(&objects.paths, &output),
+                LibType::Shared => context.compiler.link_shared(&objects.paths, &output),
+            };
+
+            context.link(&invocation, &objects, &output, name)?;
+            emit_artifact(context, name, lib_name, lib_type.into(), &output);
+            artifacts.libraries.push(output);
+        }
+    }
+
+    for target in &targets {
+        let mut exe_sources = vec![target.main.clone()];
+        exe_sources.extend(target.sources.iter().cloned());
+
+        let mut exe_objects =
+            context.compile_objects(&context.compiler, &exe_sources, &target.name)?;
+        exe_objects.extend(objects.clone());
+        exe_objects.paths.extend(context.libraries.iter().cloned());
+
+        let output = context.executable_path(&target.name);
+        let invocation = context.compiler.link(&exe_objects.paths, &output);
+
+        context.link(&invocation, &exe_objects, &output, &target.name)?;
+        emit_artifact(
+            context,
+            name,
+            &target.name,
+            ArtifactKind::Executable,
+            &output,
+        );
+        artifacts.executables.insert(target.name.clone(), output);
+    }
+
+    if artifacts.executables.is_empty() && artifacts.libraries.is_empty() {
+        tracing::warn!("Nothing to build, add `{}` or a `[lib]` table", MAIN_FILE);
+    }
+
+    for artifact in artifacts
+        .libraries
+        .iter()
+        .chain(artifacts.executables.values())
+    {
+        tracing::info!("Finished `{}`", artifact.display());
+    }
+
+    Ok(artifacts)
+}
+
+/// Executables of the project, sorted by name
+///
+/// `src/main.c` is named after the package, each `.c` file directly in
+/// `src/bin` after its file stem, and each directory in `src/bin` containing
+/// a `main.c` after the directory, with all of its sources. `[[bin]]` entries
+/// in the config add targets, or replace the source of a discovered target
+/// with the same name.
+pub fn bin_targets(root: &Path, config: &Config) -> anyhow::Result<Vec<BinTarget>> {
+    let mut targets = BTreeMap::new();
+
+    if root.join(MAIN_FILE).exists() {
+        targets.insert(
+            config.package.name.clone(),
+            (PathBuf::from(MAIN_FILE), Vec::new()),
+        );
+    }
+
+    let bin_dir = root.join(BIN_DIR);
+    let mut dirs: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    for file in find_files(&bin_dir, "c")? {
+        let relative = file.strip_prefix(root).expect("found file within root");
+        let mut components = file
+            .strip_prefix(&bin_dir)
+            .expect("found file within bin dir")
+            .iter();
+        let name = components.next().and_then(|name| name.to_str());
+
+        match (name, components.next()) {
+            (Some(_), None) => {
+                if let Some(stem) = file.file_stem().and_then(|stem| stem.to_str()) {
+                    targets.insert(stem.to_owned(), (relative.to_owned(), Vec::new()));
+                }
+            }
+            (Some(dir), Some(_)) => dirs
+                .entry(dir.to_owned())
+                .or_default()
+                .push(relative.to_owned()),
+            (None, _) => {}
+        }
+    }
+
+    for (name, mut sources) in dirs {
+        let main = Path::new(BIN_DIR).join(&name).join(BIN_DIR_MAIN);
+        if let Some(index) = sources.iter().position(|source| source == &main) {
+            sources.remove(index);
+            targets.insert(name, (main, sources));
+        }
+    }
+
+    for bin in &config.bins {
+        match &bin.path {
+            Some(path) => {
+                targets.insert(bin.name.clone(), (path.clone(), Vec::new()));
+            }
+            None => {
+                let main = Path::new(BIN_DIR).join(format!("{}.c", bin.name));
+                targets
+                    .entry(bin.name.clone())
+                    .or_insert((main, Vec::new()));
+            }
+        }
+    }
+
+    Ok(targets
+        .into_iter()
+        .map(|(name, (main, sources))| BinTarget {
+            name,
+            main,
+            sources,
+        })
+        .collect())
+}
+
+/// Warn about directories in `src/bin` with sources but no `main.c`, which
+/// are not built into any executable
+fn warn_unbuilt_bin_dirs(root: &Path) -> anyhow::Result<()> {
+    let bin_dir = root.join(BIN_DIR);
+    let entries = match fs::read_dir(&bin_dir) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(()),
+    };
+
+    for entry in entries {
+        let dir = entry
+            .with_context(|| Error::ReadDir(bin_dir.clone()))?
+            .path();
+
+        if dir.is_dir() && !dir.join(BIN_DIR_MAIN).exists() && !find_files(&dir, "c")?.is_empty() {
+            tracing::warn!(
+                "`{}` has no `{}`, its sources are not built",
+                dir.strip_prefix(root).unwrap_or(&dir).display(),
+                BIN_DIR_MAIN
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Sources compiled into every target
+///
+/// All source files except those in `src/bin` and the `main` of each target.
+pub fn shared_sources(root: &Path, targets: &[BinTarget]) -> anyhow::Result<Vec<PathBuf>> {
+    let mut sources = source_files(root)?;
+    sources.retain(|source| {
+        !source.starts_with(BIN_DIR) && !targets.iter().any(|target| &target.main == source)
+    });
+
+    Ok(sources)
+}
+
+/// All `.c` files in the source directories, relative to the project root
+pub fn source_files(root: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut sources = Vec::new();
+
+    for dir in SOURCE_DIRS {
+        for file in find_files(&root.join(dir), "c")? {
+            let relative = file.strip_prefix(root).expect("found file within root");
+            sources.push(relative.to_owned());
+        }
+    }
+
+    Ok(sources)
+}
+
+impl BuildContext {
+    pub fn new(root: &Path, config: &Config, options: &BuildOptions) -> Self {
+        let mut compiler = Compiler::new(config, options.profile);
+
+        let lib_types = config.lib.as_ref().map(|lib| lib.types.as_slice());
+        if lib_types.is_some_and(|types| types.contains(&LibType::Shared)) {
+            compiler = compiler.position_independent();
+        }
+
+        Self {
+            root: root.to_owned(),
+            out_dir: Path::new(TARGET_DIR).join(options.profile.dir_name()),
+            profile: options.profile,
+            compiler,
+            jobs: options.jobs(config),
+            package: None,
+            libraries: Vec::new(),
+        }
+    }
+
+    /// Write into the output directory of the project at `root_dir`, relative
+    /// to this one, keeping objects of the package `name` apart from others
+    pub fn share_out_dir(&mut self, root_dir: &Path, name: &str) {
+        self.out_dir = root_dir.join(&self.out_dir);
+        self.package = Some(name.to_owned());
+    }
+
+    /// Directory of object files and fingerprints, relative to the root
+    pub fn obj_dir(&self) -> PathBuf {
+        let dir = self.out_dir.join(OBJECT_DIR);
+
+        match &self.package {
+            Some(name) => dir.join(name),
+            None => dir,
+        }
+    }
+
+    /// Compile each source into its own object file in the output directory
+    ///
+    /// Sources are only recompiled if they, or a header they include, changed
+    /// since their object was built, or if the compiler flags changed. Up to
+    /// `jobs` compilers are run in parallel.
+    pub fn compile_objects(
+        &self,
+        compiler: &Compiler,
+        sources: &[PathBuf],
+        name: &str,
+    ) -> anyhow::Result<Objects> {
+        let root = self.root.as_path();
+        let units: Vec<_> = sources
+            .iter()
+            .map(|source| CompileUnit::new(compiler, source, self.object_path(source)))
+            .collect();
+
+        let stale: Vec<_> = units.iter().filter(|unit| !unit.is_fresh(root)).collect();
+
+        for unit in &stale {
+            tracing::debug!(source = ?unit.source, "Compiling");
+            create_parent_dir(root, &unit.object)?;
+        }
+
+        let results = parallel::map(&stale, self.jobs, |unit| unit.compile(root));
+
+        let mut failed = false;
+        for result in results {
+            failed |= !result?;
+        }
+
+        if failed {
+            anyhow::bail!(Error::Build(name.to_owned()));
+        }
+
+        Ok(Objects {
+            rebuilt: !stale.is_empty(),
+            paths: units.into_iter().map(|unit| unit.object).collect(),
+        })
+    }
+
+    /// Object file and full command line, including the program, used to
+    /// compile `source`
+    pub fn compile_command(&self, compiler: &Compiler, source: &Path) -> (PathBuf, Vec<OsString>) {
+        let unit = CompileUnit::new(compiler, source, self.object_path(source));
+        (unit.object, unit.invocation.command_line())
+    }
+
+    /// Link `objects` into `output` with `invocation`, unless it is already up
+    /// to date
+    pub fn link(
+        &self,
+        invocation: &Invocation,
+        objects: &Objects,
+        output: &Path,
+        name: &str,
+    ) -> anyhow::Result<()> {
+        let fingerprint = invocation.fingerprint();
+        let fingerprint_path = self.link_fingerprint_path(output);
+
+        if !objects.rebuilt
+            && fingerprint::is_fresh(
+                &self.root,
+                output,
+                &fingerprint_path,
+                &fingerprint,
+                &objects.paths,
+            )
+        {
+            tracing::debug!(?output, "Fresh");
+            return Ok(());
+        }
+
+        tracing::debug!(?output, "Linking");
+
+        // archivers add to an existing file instead of replacing it
+        let _ = fs::remove_file(self.root.join(output));
+
+        if !invocation.run(&self.root)? {
+            anyhow::bail!(Error::Build(name.to_owned()));
+        }
+
+        fingerprint::record(&self.root, &fingerprint_path, &fingerprint);
+
+        Ok(())
+    }
+
+    /// Path of the executable `name` within the output directory
+    pub fn executable_path(&self, name: &str) -> PathBuf {
+        self.out_dir.join(format!("{}{}", name, EXE_SUFFIX))
+    }
+
+    /// Path of the library `name` of `lib_type` within the output directory
+    pub fn library_path(&self, name: &str, lib_type: LibType) -> PathBuf {
+        let file_name = match lib_type {
+            LibType::Static => format!("lib{}.a", name),
+            LibType::Shared => format!("{}{}{}", DLL_PREFIX, name, DLL_SUFFIX),
+        };
+
+        self.out_dir.join(file_name)
+    }
+
+    /// Create the output directory, and the object directory within it
+    pub fn create_out_dir(&self) -> anyhow::Result<()> {
+        let path = self.root.join(self.obj_dir());
+
+        tracing::debug!(?path, "Creating output directory");
+        fs::create_dir_all(&path).with_context(|| Error::CreateDir(path.to_owned()))
+    }
+
+    /// Object file for `source`, mirroring its path within the object directory
+    fn object_path(&self, source: &Path) -> PathBuf {
+        self.obj_dir().join(source).with_extension("o")
+    }
+
+    fn link_fingerprint_path(&self, output: &Path) -> PathBuf {
+        let mut file_name = output.file_name().unwrap_or_default().to_owned();
+        file_name.push(".cmd");
+
+        self.obj_dir().join(file_name)
+    }
+}
+
+/// A source file and the object file it compiles to
+struct CompileUnit<'a> {
+    source: &'a Path,
+    object: PathBuf,
+    fingerprint_path: PathBuf,
+    depfile: PathBuf,
+    invocation: Invocation,
+    fingerprint: String,
+}
+
+impl<'a> CompileUnit<'a> {
+    fn new(compiler: &Compiler, source: &'a Path, object: PathBuf) -> Self {
+        let depfile = object.with_extension("d");
+        let fingerprint_path = object.with_extension("cmd");
+        let invocation = compiler.compile(source, &object, &depfile);
+        let fingerprint = invocation.fingerprint();
+
+        Self {
+            source,
+            object,
+            fingerprint_path,
+            depfile,
+            invocation,
+            fingerprint,
+        }
+    }
+
+    fn is_fresh(&self, root: &Path) -> bool {
+        let deps = match fingerprint::read_depfile(root, &self.depfile) {
+            Some(deps) if !deps.is_empty() => deps,
+            _ => return false,
+        };
+
+        fingerprint::is_fresh(
+            root,
+            &self.object,
+            &self.fingerprint_path,
+            &self.fingerprint,
+            &deps,
+        )
+    }
+
+    fn compile(&self, root: &Path) -> anyhow::Result<bool> {
+        let success = self.invocation.run(root)?;
+
+        if success {
+            fingerprint::record(root, &self.fingerprint_path, &self.fingerprint);
+        }
+
+        Ok(success)
+    }
+}
+
+fn create_parent_dir(root: &Path, file: &Path) -> anyhow::Result<()> {
+    let dir = match file.parent() {
+        Some(parent) => root.join(parent),
+        None => return Ok(()),
+    };
+
+    fs::create_dir_all(&dir).with_context(|| Error::CreateDir(dir.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        service::{config::Bin, init::ProjectKind},
+        util::test::{testdir_and_path, testdir_project_kind},
+    };
+
+    #[test]
+    fn source_files_only_src_and_lib() {
+        let (_dir, path) = testdir_and_path();
+        for dir in ["src", "lib/util", "tests"] {
+            fs::create_dir_all(path.join(dir)).expect("create dir");
+        }
+        for file in ["src/main.c", "src/main.h", "lib/util/str.c", "tests/run.c"] {
+            fs::write(path.join(file), "").expect("write file");
+        }
+
+        let sources = source_files(&path).expect("find sources");
+
+        let expected = vec![
+            PathBuf::from("src").join("main.c"),
+            PathBuf::from("lib").join("util").join("str.c"),
+        ];
+        assert_eq!(expected, sources);
+    }
+
+    #[test]
+    fn bin_targets_discovered_and_configured() {
+        let (_dir, path) = testdir_and_path();
+        fs::create_dir_all(path.join(BIN_DIR).join("nested")).expect("create dirs");
+        fs::create_dir_all(path.join(BIN_DIR).join("proxy").join("net")).expect("create dirs");
+        for file in [
+            "src/main.c",
+            "src/bin/server.c",
+            "src/bin/nested/util.c",
+            "src/bin/proxy/main.c",
+            "src/bin/proxy/net/conn.c",
+        ] {
+            fs::write(path.join(file), "").expect("write file");
+        }
+        let mut config = Config::default();
+        config.package.name = "app".to_owned();
+        config.bins.push(Bin {
+            name: "client".to_owned(),
+            path: Some(PathBuf::from("tools/client.c")),
+        });
+
+        let targets = bin_targets(&path, &config).expect("bin targets");
+
+        let expected = vec![
+            BinTarget {
+                name: "app".to_owned(),
+                main: PathBuf::from(MAIN_FILE),
+                sources: Vec::new(),
+            },
+            BinTarget {
+                name: "client".to_owned(),
+                main: PathBuf::from("tools/client.c"),
+                sources: Vec::new(),
+            },
+            BinTarget {
+                name: "proxy".to_owned(),
+                main: Path::new(BIN_DIR).join("proxy").join("main.c"),
+                sources: vec![Path::new(BIN_DIR).join("proxy").join("net").join("conn.c")],
+            },
+            BinTarget {
+                name: "server".to_owned(),
+                main: Path::new(BIN_DIR).join("server.c"),
+                sources: Vec::new(),
+            },
+        ];
+        assert_eq!(expected, targets);
+
+        let sources = shared_sources(&path, &targets).expect("shared sources");
+        assert!(sources.is_empty(), "no shared sources: {:?}", sources);
+    }
+
+    #[test]
+    fn object_path_mirrors_source() {
+        let context = test_context(Path::new("."), Profile::Debug);
+        let object = context.object_path(&PathBuf::from("lib").join("util").join("str.c"));
+        let expected = PathBuf::from("target/debug/obj/lib/util/str.o");
+        assert_eq!(expected, object);
+    }
+
+    #[test]
+    fn release_output_dir() {
+        let context = test_context(Path::new("."), Profile::Release);
+        let expected = PathBuf::from("target/release/name");
+        assert_eq!(expected, context.executable_path("name").with_extension(""));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn build_project_creates_executable() {
+        let (_dir, mut path) = crate::util::test::testdir_project();
+        let config = Config::load_from_dir(&mut path).expect("load config");
+
+        let artifacts = build_package(
+            &BuildContext::new(&path, &config, &BuildOptions::default()),
+            &config,
+        )
+        .expect("build project");
+
+        let expected = Path::new(TARGET_DIR)
+            .join("debug")
+            .join(format!("{}{}", config.package.name, EXE_SUFFIX));
+        assert_eq!(
+            Some(&expected),
+            artifacts.executables.get(&config.package.name)
+        );
+        assert!(artifacts.libraries.is_empty());
+        assert!(path.join(&expected).exists(), "executable should exist");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn build_project_links_bin_directory() {
+        let (_dir, mut path) = crate::util::test::testdir_project();
+        let config = Config::load_from_dir(&mut path).expect("load config");
+        let bin_dir = path.join(BIN_DIR).join("calc");
+        fs::create_dir_all(&bin_dir).expect("create bin dir");
+        let main = "int calc_answer(void);\nint main(void) { return calc_answer(); }\n";
+        fs::write(bin_dir.join("main.c"), main).expect("write main");
+        fs::write(
+            bin_dir.join("answer.c"),
+            "int calc_answer(void) { return 42; }\n",
+        )
+        .expect("write source");
+
+        let artifacts = build_package(
+            &BuildContext::new(&path, &config, &BuildOptions::default()),
+            &config,
+        )
+        .expect("build project");
+
+        let exe = path.join(&artifacts.executables["calc"]);
+        let status = std::process::Command::new(exe).status().expect("run calc");
+        assert_eq!(Some(42), status.code());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn build_project_creates_libraries() {
+        let (_dir, mut path) = testdir_project_kind(ProjectKind::Lib);
+        let mut config = Config::load_from_dir(&mut path).expect("load config");
+        config.lib.as_mut().expect("lib config").types = vec![LibType::Static, LibType::Shared];
+
+        let artifacts = build_package(
+            &BuildContext::new(&path, &config, &BuildOptions::default()),
+            &config,
+        )
+        .expect("build project");
+
+        let out_dir = Path::new(TARGET_DIR).join("debug");
+        let expected = vec![
+            out_dir.join(format!("lib{}.a", config.package.name)),
+            out_dir.join(format!(
+                "{}{}{}",
+                DLL_PREFIX, config.package.name, DLL_SUFFIX
+            )),
+        ];
+        assert_eq!(expected, artifacts.libraries);
+        assert!(artifacts.executables.is_empty());
+        for library in &expected {
+            assert!(
+                path.join(library).exists(),
+                "library should exist: `{}`",
+                library.display()
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn build_project_compile_error() {
+        let (_dir, mut path) = crate::util::test::testdir_project();
+        let config = Config::load_from_dir(&mut path).expect("load config");
+        fs::write(path.join("src").join("main.c"), "int main(void) {").expect("write main");
+
+        let err = build_package(
+            &BuildContext::new(&path, &config, &BuildOptions::default()),
+            &config,
+        )
+        .expect_err("compile error");
+
+        let err = err.downcast_ref::<Error>().expect("crame error");
+        assert!(matches!(err, Error::Build(_)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn compile_objects_only_rebuilds_changed() {
+        let (_dir, path) = crate::util::test::testdir_project();
+        fs::write(path.join("src/greet.h"), "int greet(void);\n").expect("write header");
+        fs::write(
+            path.join("src/greet.c"),
+            "#include \"greet.h\"\nint greet(void) { return 1; }\n",
+        )
+        .expect("write source");
+        let context = test_context(&path, Profile::Debug);
+        context.create_out_dir().expect("create out dir");
+        let sources = source_files(&path).expect("find sources");
+        let main_object = path.join(context.object_path(Path::new("src/main.c")));
+        let compile = || {
+            context
+                .compile_objects(&context.compiler, &sources, "test")
+                .expect("compile")
+        };
+
+        let first = compile();
+        let main_built = modified(&main_object);
+        let second = compile();
+        touch_later(&path.join("src/greet.h"));
+        let third = compile();
+
+        assert!(first.rebuilt);
+        assert!(!second.rebuilt);
+        assert!(third.rebuilt);
+        assert_eq!(main_built, modified(&main_object));
+    }
+
+    fn test_context(root: &Path, profile: Profile) -> BuildContext {
+        let options = BuildOptions {
+            profile,
+            ..BuildOptions::default()
+        };
+        BuildContext::new(root, &Config::default(), &options)
+    }
+
+    #[cfg(unix)]
+    fn modified(path: &Path) -> std::time::SystemTime {
+        fs::metadata(path)
+            .and_then(|meta| meta.modified())
+            .expect("modified time")
+    }
+
+    #[cfg(unix)]
+    fn touch_later(path: &Path) {
+        let later = std::time::SystemTime::now() + std::time::Duration::from_secs(60);
+        let file = fs::File::options()
+            .write(true)
+            .open(path)
+            .expect("open file");
+        file.set_modified(later).expect("touch file");
+    }
+}
@@ -6,18 +6,13 @@ use serde::{Deserialize, Serialize};
 
 use std::{fs, io::Write, path::PathBuf};
 
-#[derive(Debug, Clone, Copy, ValueEnum, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Copy, ValueEnum, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum BuildSystem {
+    #[default]
     Just,
 }
 
-impl Default for BuildSystem {
-    fn default() -> Self {
-        BuildSystem::Just
-    }
-}
-
 pub fn init_build_system(build_system: BuildSystem, path: &mut PathBuf) -> anyhow::Result<()> {
     match build_system {
         BuildSystem::Just => create_justfile(path),
@@ -28,6 +23,14 @@ const JUSTFILE_CONTENTS: &[u8] = include_bytes!("../../template/justfile");
 
 fn create_justfile(path: &mut PathBuf) -> anyhow::Result<()> {
     path.push("justfile");
+
+    // such as one from a template
+    if path.exists() {
+        tracing::debug!(?path, "Keeping existing justfile");
+        path.pop();
+        return Ok(());
+    }
+
     tracing::debug!(?path, "Writing justfile");
 
     let mut dest = fs::File::create(&path).with_context(|| Error::CreateFile(path.to_owned()))?;
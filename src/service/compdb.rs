@@ -0,0 +1,187 @@
+use super::{
+    build::{bin_targets, source_files, BuildContext},
+    compiler::Compiler,
+    config::Config,
+    test::{discover_tests, include_path, runner_compiler, RUNNER_FILE},
+};
+use crate::util::error::Error;
+
+use anyhow::Context;
+use serde::Serialize;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const COMPDB_FILE: &str = "compile_commands.json";
+/// Macro naming the test file a runner checks in the compilation database,
+/// relative to the runner
+const EDITOR_MACRO: &str = "CRAME_EDITOR";
+
+/// Entry of a JSON compilation database
+#[derive(Debug, Serialize)]
+pub struct CompileCommand {
+    pub directory: PathBuf,
+    pub file: PathBuf,
+    pub arguments: Vec<String>,
+    pub output: PathBuf,
+}
+
+/// Write `compile_commands.json` to the project root
+///
+/// It contains every source of the executable and test targets, with the
+/// same flags the build uses. The file is only rewritten when its contents
+/// change, such as after editing `Crame.toml`.
+pub fn update(context: &BuildContext, config: &Config) -> anyhow::Result<()> {
+    let commands = compile_commands(context, config)?;
+    let contents = serde_json::to_string_pretty(&commands)? + "\n";
+    let path = context.root.join(COMPDB_FILE);
+
+    if fs::read_to_string(&path).ok().as_deref() != Some(contents.as_str()) {
+        tracing::debug!(?path, "Writing compilation database");
+        fs::write(&path, contents).with_context(|| Error::WriteFile(path.to_owned()))?;
+    }
+
+    Ok(())
+}
+
+/// Compile commands for the executable and test targets
+pub fn compile_commands(
+    context: &BuildContext,
+    config: &Config,
+) -> anyhow::Result<Vec<CompileCommand>> {
+    let mut sources = source_files(&context.root)?;
+    for target in bin_targets(&context.root, config)? {
+        if !sources.contains(&target.main) {
+            sources.push(target.main);
+        }
+    }
+
+    let mut commands: Vec<_> = sources
+        .iter()
+        .map(|source| compile_command(context, &context.compiler, source))
+        .collect();
+
+    let runner = Path::new(RUNNER_FILE);
+    if context.root.join(runner).exists() {
+        let runner_compiler = runner_compiler(context, config);
+        commands.push(compile_command(context, &runner_compiler, runner));
+
+        if has_editor_mode(&context.root.join(runner)) {
+            for test in discover_tests(&context.root)? {
+                let compiler = test_file_compiler(&runner_compiler, &test);
+                commands.push(compile_command(context, &compiler, &test));
+            }
+        }
+    }
+
+    Ok(commands)
+}
+
+/// Whether the runner at `path` can check a test file on its own, which
+/// runners of older projects cannot
+fn has_editor_mode(path: &Path) -> bool {
+    fs::read_to_string(path).is_ok_and(|contents| contents.contains(EDITOR_MACRO))
+}
+
+/// Compiler checking the test file `test` on its own, as editors do
+///
+/// The runner is included first, and includes the `HEADERS` section of the
+/// file named by `CRAME_EDITOR`, so its tests are checked with the framework
+/// and headers they use.
+fn test_file_compiler(runner_compiler: &Compiler, test: &Path) -> Compiler {
+    let runner_dir = Path::new(RUNNER_FILE).parent().unwrap_or(Path::new(""));
+    let from_runner = test.strip_prefix(runner_dir).unwrap_or(test);
+
+    runner_compiler
+        .clone()
+        .define(EDITOR_MACRO, &format!("\"{}\"", include_path(from_runner)))
+        .force_include(RUNNER_FILE)
+}
+
+fn compile_command(context: &BuildContext, compiler: &Compiler, source: &Path) -> CompileCommand {
+    let (output, arguments) = context.compile_command(compiler, source);
+
+    CompileCommand {
+        directory: context.root.clone(),
+        file: source.to_owned(),
+        arguments: arguments
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect(),
+        output,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::{build::BuildOptions, config::Config, test::UNIT_DIR};
+    use crate::util::test::testdir_project;
+
+    #[test]
+    fn commands_for_sources_and_runner() {
+        let (_dir, path) = testdir_project();
+        let config = Config::default();
+        let context = BuildContext::new(&path, &config, &BuildOptions::default());
+
+        let commands = compile_commands(&context, &config).expect("compile commands");
+
+        let files: Vec<_> = commands.iter().map(|command| &command.file).collect();
+        let test = Path::new(UNIT_DIR).join("it_works.c");
+        assert_eq!(
+            vec![Path::new("src/main.c"), Path::new(RUNNER_FILE), &test],
+            files
+        );
+        assert_eq!("cc", commands[0].arguments[0]);
+        assert!(commands[1]
+            .arguments
+            .contains(&"-Itarget/debug/obj".to_owned()));
+        let test_args = &commands[2].arguments;
+        assert!(test_args.contains(&"-DCRAME_EDITOR=\"unit/it_works.c\"".to_owned()));
+        assert!(test_args
+            .windows(2)
+            .any(|args| args == ["-include", RUNNER_FILE]));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_command_checks_tests() {
+        let (_dir, path) = testdir_project();
+        let config = Config::default();
+        let context = BuildContext::new(&path, &config, &BuildOptions::default());
+        let tests = "#if defined HEADERS\n#include <string.h>\n#elif defined TESTS\n\
+            TEST(\"compares\", .timeout = 1) { ASSERT_EQ(strlen(\"ab\"), 2); }\n\
+            #endif\n";
+        fs::write(path.join(UNIT_DIR).join("str.c"), tests).expect("write test");
+
+        let commands = compile_commands(&context, &config).expect("compile commands");
+
+        for command in &commands[2..] {
+            let obj_dir = path.join(command.output.parent().expect("object in a directory"));
+            fs::create_dir_all(obj_dir).expect("create object dir");
+
+            let status = std::process::Command::new(&command.arguments[0])
+                .args(&command.arguments[1..])
+                .arg("-Werror")
+                .current_dir(&command.directory)
+                .status()
+                .expect("run compiler");
+            assert!(status.success(), "{:?}", command.file);
+        }
+    }
+
+    #[test]
+    fn update_writes_file() {
+        let (_dir, path) = testdir_project();
+        let config = Config::default();
+        let context = BuildContext::new(&path, &config, &BuildOptions::default());
+
+        update(&context, &config).expect("update compilation database");
+
+        let contents = fs::read_to_string(path.join(COMPDB_FILE)).expect("read compdb");
+        let json: serde_json::Value = serde_json::from_str(&contents).expect("valid json");
+        assert_eq!(3, json.as_array().expect("array of commands").len());
+    }
+}
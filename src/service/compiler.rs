@@ -0,0 +1,411 @@
+use super::{
+    config::{CStandard, CompilerProgram, Config},
+    profile::Profile,
+};
+use crate::util::{error::Error, file::lexical_normalize, message};
+
+use anyhow::Context;
+use serde::Serialize;
+
+use std::{
+    ffi::OsString,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+const ARCHIVER: &str = "ar";
+
+/// C compiler and the flags it is invoked with
+#[derive(Debug, Clone)]
+pub struct Compiler {
+    program: PathBuf,
+    cflags: Vec<String>,
+    ldflags: Vec<String>,
+}
+
+impl Compiler {
+    /// Compiler configured by the `[build]` table and a build `profile`
+    pub fn new(config: &Config, profile: Profile) -> Self {
+        let build = &config.build;
+        let program = build.compiler.clone().unwrap_or(CompilerProgram::Cc);
+        let mut cflags = Vec::new();
+        let mut ldflags = Vec::new();
+
+        if let Some(std) = build.std {
+            cflags.push(std.flag().to_owned());
+        }
+
+        let warnings = build.warnings.unwrap_or_default();
+        cflags.extend(warnings.flags().iter().map(|&flag| flag.to_owned()));
+
+        if build.deny_warnings {
+            cflags.push("-Werror".to_owned());
+        }
+
+        for dir in &build.include_dirs {
+            cflags.push(format!("-I{}", dir.display()));
+        }
+
+        for dir in &build.lib_dirs {
+            ldflags.push(format!("-L{}", dir.display()));
+        }
+
+        cflags.extend(profile.cflags(config));
+        ldflags.extend(profile.ldflags(config));
+
+        Self {
+            program: program.program().to_owned(),
+            cflags,
+            ldflags,
+        }
+    }
+
+    /// Add a directory to the header search path
+    pub fn include_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.cflags.push(format!("-I{}", dir.as_ref().display()));
+        self
+    }
+
+    /// Compile as `std` instead of the configured standard
+    pub fn std(mut self, std: CStandard) -> Self {
+        self.cflags.retain(|flag| !flag.starts_with("-std="));
+        self.cflags.push(std.flag().to_owned());
+        self
+    }
+
+    /// Define the preprocessor macro `name` as `value`
+    pub fn define(mut self, name: &str, value: &str) -> Self {
+        self.cflags.push(format!("-D{}={}", name, value));
+        self
+    }
+
+    /// Include `header` before the contents of each source
+    pub fn force_include<P: AsRef<Path>>(mut self, header: P) -> Self {
+        self.cflags.push("-include".to_owned());
+        self.cflags.push(header.as_ref().display().to_string());
+        self
+    }
+
+    /// Compile position independent code, as needed by shared libraries
+    pub fn position_independent(mut self) -> Self {
+        self.cflags.push("-fPIC".to_owned());
+        self
+    }
+
+    /// Let executables find shared libraries in their own directory at run
+    /// time, as Windows does by default
+    pub fn rpath_origin(mut self) -> Self {
+        if cfg!(target_os = "macos") {
+            self.ldflags.push("-Wl,-rpath,@loader_path".to_owned());
+        } else if cfg!(unix) {
+            self.ldflags.push("-Wl,-rpath,$ORIGIN".to_owned());
+        }
+        self
+    }
+
+    /// Compile `source` into `object`, writing its header dependencies to
+    /// `depfile`
+    pub fn compile(&self, source: &Path, object: &Path, depfile: &Path) -> Invocation {
+        let mut args: Vec<OsString> = vec!["-c".into(), source.into(), "-o".into(), object.into()];
+        args.extend(["-MMD".into(), "-MF".into(), depfile.into()]);
+        args.extend(self.cflags.iter().map(OsString::from));
+
+        self.invocation(args)
+    }
+
+    /// Link `objects` into the executable `output`
+    pub fn link(&self, objects: &[PathBuf], output: &Path) -> Invocation {
+        let mut args: Vec<OsString> = objects.iter().map(OsString::from).collect();
+        args.extend(["-o".into(), output.into()]);
+        args.extend(self.ldflags.iter().map(OsString::from));
+
+        self.invocation(args)
+    }
+
+    /// Link `objects` into the shared library `output`
+    ///
+    /// The library is named by its file name, rather than the path it is
+    /// linked with, so executables look it up in their run time search path.
+    pub fn link_shared(&self, objects: &[PathBuf], output: &Path) -> Invocation {
+        let mut invocation = self.link(objects, output);
+        invocation.args.insert(0, "-shared".into());
+
+        let file_name = output.file_name().unwrap_or_default().to_string_lossy();
+        if cfg!(target_os = "macos") {
+            let flag = format!("-Wl,-install_name,@rpath/{}", file_name);
+            invocation.args.push(flag.into());
+        } else if cfg!(unix) {
+            invocation
+                .args
+                .push(format!("-Wl,-soname,{}", file_name).into());
+        }
+
+        invocation
+    }
+
+    /// Archive `objects` into the static library `output`
+    pub fn archive(&self, objects: &[PathBuf], output: &Path) -> Invocation {
+        let mut args: Vec<OsString> = vec!["rcs".into(), output.into()];
+        args.extend(objects.iter().map(OsString::from));
+
+        Invocation {
+            program: PathBuf::from(ARCHIVER),
+            args,
+        }
+    }
+
+    fn invocation(&self, args: Vec<OsString>) -> Invocation {
+        Invocation {
+            program: self.program.clone(),
+            args,
+        }
+    }
+}
+
+/// Output of the compiler, printed with `--message-format json`
+#[derive(Debug, Serialize)]
+#[serde(tag = "reason", rename = "compiler-message")]
+struct CompilerMessage {
+    /// Output as the compiler printed it
+    rendered: String,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Diagnostic in the `file:line:column: level: message` form of gcc and clang
+#[derive(Debug, PartialEq, Eq, Serialize)]
+struct Diagnostic {
+    /// Absolute path of the file
+    file: PathBuf,
+    line: u32,
+    column: u32,
+    /// `error`, `warning`, `note` or `fatal error`
+    level: String,
+    message: String,
+}
+
+impl CompilerMessage {
+    /// Message for `rendered` output of a compiler run in `root`
+    fn new(root: &Path, rendered: String) -> Self {
+        let diagnostics = rendered
+            .lines()
+            .filter_map(|line| parse_diagnostic(root, line))
+            .collect();
+
+        Self {
+            rendered,
+            diagnostics,
+        }
+    }
+}
+
+fn parse_diagnostic(root: &Path, line: &str) -> Option<Diagnostic> {
+    let (location, rest) = line.split_once(": ")?;
+    let (level, message) = rest.split_once(": ")?;
+
+    if !matches!(level, "error" | "warning" | "note" | "fatal error") {
+        return None;
+    }
+
+    let mut parts = location.rsplitn(3, ':');
+    let column = parts.next()?.parse().ok()?;
+    let line = parts.next()?.parse().ok()?;
+    let file = lexical_normalize(&root.join(parts.next()?));
+
+    Some(Diagnostic {
+        file,
+        line,
+        column,
+        level: level.to_owned(),
+        message: message.to_owned(),
+    })
+}
+
+/// Program and arguments for a single step of a build
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invocation {
+    pub program: PathBuf,
+    pub args: Vec<OsString>,
+}
+
+impl Invocation {
+    /// Run the program
+    ///
+    /// Paths are relative to `root`, which is used as the working directory.
+    /// Output is captured and written to stderr in one piece, so output from
+    /// compilers running in parallel is not interleaved. Returns `false` if
+    /// the program reported an error.
+    pub fn run(&self, root: &Path) -> anyhow::Result<bool> {
+        let mut command = Command::new(&self.program);
+        command.current_dir(root).args(&self.args);
+
+        tracing::debug!(?command, "Running compiler");
+
+        let output = command
+            .output()
+            .with_context(|| Error::Compiler(self.program.display().to_string()))?;
+
+        if !output.stdout.is_empty() || !output.stderr.is_empty() {
+            if message::is_json() {
+                let mut rendered = String::from_utf8_lossy(&output.stdout).into_owned();
+                rendered += &String::from_utf8_lossy(&output.stderr);
+                message::emit(&CompilerMessage::new(root, rendered));
+            } else {
+                let mut stderr = io::stderr().lock();
+                // diagnostics are best effort, the exit status decides success
+                let _ = stderr.write_all(&output.stdout);
+                let _ = stderr.write_all(&output.stderr);
+            }
+        }
+
+        Ok(output.status.success())
+    }
+
+    /// Text identifying this invocation, used to detect changes in flags
+    /// between builds
+    pub fn fingerprint(&self) -> String {
+        let mut fingerprint = self.program.display().to_string();
+
+        for arg in &self.args {
+            fingerprint.push('\n');
+            fingerprint.push_str(&arg.to_string_lossy());
+        }
+
+        fingerprint
+    }
+
+    /// Program followed by its arguments
+    pub fn command_line(&self) -> Vec<OsString> {
+        let mut command_line = vec![self.program.as_os_str().to_owned()];
+        command_line.extend(self.args.iter().cloned());
+        command_line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::config::{CStandard, Warnings};
+
+    #[test]
+    fn compiler_message_diagnostics() {
+        let rendered = "src/a.c: In function 'main':\n\
+            src/a.c:3:9: warning: unused variable 'x' [-Wunused-variable]\n    \
+            3 |     int x;\n\
+            src/../include/a.h:1:1: error: unknown type name 'foo'\n\
+            /usr/bin/ld: a.o: in function `main':\n";
+
+        let message = CompilerMessage::new(Path::new("/p"), rendered.to_owned());
+
+        let expected = vec![
+            Diagnostic {
+                file: PathBuf::from("/p/src/a.c"),
+                line: 3,
+                column: 9,
+                level: "warning".to_owned(),
+                message: "unused variable 'x' [-Wunused-variable]".to_owned(),
+            },
+            Diagnostic {
+                file: PathBuf::from("/p/include/a.h"),
+                line: 1,
+                column: 1,
+                level: "error".to_owned(),
+                message: "unknown type name 'foo'".to_owned(),
+            },
+        ];
+        assert_eq!(expected, message.diagnostics);
+    }
+
+    #[test]
+    fn default_compile_args() {
+        let compiler = Compiler::new(&Config::default(), Profile::Debug);
+
+        let invocation = compiler.compile(Path::new("a.c"), Path::new("a.o"), Path::new("a.d"));
+
+        let expected = [
+            "-c", "a.c", "-o", "a.o", "-MMD", "-MF", "a.d", "-Wall", "-O0", "-g",
+        ];
+        assert_eq!(Path::new("cc"), invocation.program);
+        assert_eq!(os_strings(&expected), invocation.args);
+    }
+
+    #[test]
+    fn configured_compile_args() {
+        let mut config = Config::default();
+        config.build.compiler = Some(CompilerProgram::Clang);
+        config.build.std = Some(CStandard::C99);
+        config.build.warnings = Some(Warnings::Pedantic);
+        config.build.deny_warnings = true;
+        config.build.include_dirs = vec![PathBuf::from("include")];
+        let compiler = Compiler::new(&config, Profile::Release);
+
+        let invocation = compiler.compile(Path::new("a.c"), Path::new("a.o"), Path::new("a.d"));
+
+        let expected = [
+            "-c",
+            "a.c",
+            "-o",
+            "a.o",
+            "-MMD",
+            "-MF",
+            "a.d",
+            "-std=c99",
+            "-Wall",
+            "-Wextra",
+            "-Wpedantic",
+            "-Werror",
+            "-Iinclude",
+            "-O2",
+            "-DNDEBUG",
+        ];
+        assert_eq!(Path::new("clang"), invocation.program);
+        assert_eq!(os_strings(&expected), invocation.args);
+    }
+
+    #[test]
+    fn configured_link_args() {
+        let mut config = Config::default();
+        config.build.lib_dirs = vec![PathBuf::from("vendor")];
+        config.profile.debug.ldflags = vec!["-lm".to_owned()];
+        let compiler = Compiler::new(&config, Profile::Debug);
+
+        let invocation = compiler.link(&[PathBuf::from("a.o")], Path::new("a"));
+
+        assert_eq!(
+            os_strings(&["a.o", "-o", "a", "-Lvendor", "-lm"]),
+            invocation.args
+        );
+    }
+
+    #[test]
+    fn shared_link_args() {
+        let compiler = Compiler::new(&Config::default(), Profile::Debug);
+
+        let invocation = compiler.link_shared(&[PathBuf::from("a.o")], Path::new("liba.so"));
+
+        assert_eq!(
+            os_strings(&["-shared", "a.o", "-o", "liba.so"]),
+            invocation.args[..4]
+        );
+        #[cfg(all(unix, not(target_os = "macos")))]
+        assert_eq!(os_strings(&["-Wl,-soname,liba.so"]), invocation.args[4..]);
+    }
+
+    #[test]
+    fn archive_args() {
+        let compiler = Compiler::new(&Config::default(), Profile::Debug);
+        let objects = &[PathBuf::from("a.o"), PathBuf::from("b.o")];
+
+        let invocation = compiler.archive(objects, Path::new("liba.a"));
+
+        assert_eq!(Path::new("ar"), invocation.program);
+        assert_eq!(
+            os_strings(&["rcs", "liba.a", "a.o", "b.o"]),
+            invocation.args
+        );
+    }
+
+    fn os_strings(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+}
@@ -5,16 +5,65 @@ use anyhow::Context;
 use serde::{Deserialize, Serialize};
 
 use std::{
+    collections::BTreeMap,
     fs,
+    num::{NonZeroU64, NonZeroUsize},
     path::{Path, PathBuf},
+    time::Duration,
 };
 
-const CONFIG_PATH: &str = "Crame.toml";
+pub const CONFIG_PATH: &str = "Crame.toml";
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub struct Config {
     pub package: Package,
+
+    #[serde(default, skip_serializing_if = "Build::is_default")]
+    pub build: Build,
+
+    #[serde(default, skip_serializing_if = "Profiles::is_default")]
+    pub profile: Profiles,
+
+    #[serde(default, skip_serializing_if = "Test::is_default")]
+    pub test: Test,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lib: Option<Lib>,
+
+    #[serde(default, rename = "bin", skip_serializing_if = "Vec::is_empty")]
+    pub bins: Vec<Bin>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub dependencies: BTreeMap<String, Dependency>,
+}
+
+/// Contents of a `Crame.toml`, which describes either a package or a workspace
+#[derive(Debug)]
+pub enum Manifest {
+    Package(Box<Config>),
+    Workspace(WorkspaceConfig),
+}
+
+/// Packages built together into the workspace's `target` directory
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct WorkspaceConfig {
+    /// Package directories, relative to the workspace root
+    pub members: Vec<PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WorkspaceManifest {
+    workspace: WorkspaceConfig,
+}
+
+/// Another package whose library target is linked into this one
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct Dependency {
+    /// Package directory, relative to this package
+    pub path: PathBuf,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -23,6 +72,319 @@ pub struct Package {
     pub build_system: BuildSystem,
 }
 
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct Build {
+    /// Number of files to compile in parallel, defaults to the number of CPUs
+    pub jobs: Option<NonZeroUsize>,
+
+    /// C compiler, defaults to `cc`
+    pub compiler: Option<CompilerProgram>,
+
+    /// C language standard, defaults to the compiler's default
+    pub std: Option<CStandard>,
+
+    /// Warning preset, defaults to `default`
+    pub warnings: Option<Warnings>,
+
+    /// Treat warnings as errors
+    #[serde(default, alias = "deny-warnings", skip_serializing_if = "is_false")]
+    pub deny_warnings: bool,
+
+    /// Additional header search paths, relative to the project root
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include_dirs: Vec<PathBuf>,
+
+    /// Additional library search paths, relative to the project root
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lib_dirs: Vec<PathBuf>,
+}
+
+/// C compiler, one of the supported names or a path to an executable
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum CompilerProgram {
+    Cc,
+    Gcc,
+    Clang,
+    Tcc,
+    Path(PathBuf),
+}
+
+impl CompilerProgram {
+    /// Program to execute
+    pub fn program(&self) -> &Path {
+        match self {
+            Self::Cc => Path::new("cc"),
+            Self::Gcc => Path::new("gcc"),
+            Self::Clang => Path::new("clang"),
+            Self::Tcc => Path::new("tcc"),
+            Self::Path(path) => path,
+        }
+    }
+}
+
+impl TryFrom<String> for CompilerProgram {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "cc" => Ok(Self::Cc),
+            "gcc" => Ok(Self::Gcc),
+            "clang" => Ok(Self::Clang),
+            "tcc" => Ok(Self::Tcc),
+            path if path.contains('/') || path.contains(std::path::MAIN_SEPARATOR) => {
+                Ok(Self::Path(PathBuf::from(path)))
+            }
+            _ => Err(format!(
+                "unknown compiler `{}`, expected `cc`, `gcc`, `clang`, `tcc` or a path",
+                value
+            )),
+        }
+    }
+}
+
+impl From<CompilerProgram> for String {
+    fn from(compiler: CompilerProgram) -> Self {
+        compiler.program().to_string_lossy().into_owned()
+    }
+}
+
+/// C language standard
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CStandard {
+    C89,
+    C99,
+    C11,
+    C17,
+    C2x,
+}
+
+impl CStandard {
+    /// Compiler flag selecting this standard
+    pub fn flag(self) -> &'static str {
+        match self {
+            Self::C89 => "-std=c89",
+            Self::C99 => "-std=c99",
+            Self::C11 => "-std=c11",
+            Self::C17 => "-std=c17",
+            Self::C2x => "-std=c2x",
+        }
+    }
+
+    /// Name of the standard, as written in `Crame.toml`
+    pub fn name(self) -> &'static str {
+        self.flag().trim_start_matches("-std=")
+    }
+}
+
+/// Set of compiler warnings to enable
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Warnings {
+    None,
+    #[default]
+    Default,
+    Strict,
+    Pedantic,
+}
+
+impl Warnings {
+    /// Compiler flags enabling this preset
+    pub fn flags(self) -> &'static [&'static str] {
+        match self {
+            Self::None => &[],
+            Self::Default => &["-Wall"],
+            Self::Strict => &["-Wall", "-Wextra"],
+            Self::Pedantic => &["-Wall", "-Wextra", "-Wpedantic"],
+        }
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+impl Build {
+    fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct Test {
+    /// Seconds a test may run before it is stopped, defaults to 60
+    pub timeout: Option<NonZeroU64>,
+}
+
+impl Test {
+    const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+    /// Time a test may run, unless it sets its own timeout
+    pub fn timeout(&self) -> Duration {
+        self.timeout.map_or(Self::DEFAULT_TIMEOUT, |secs| {
+            Duration::from_secs(secs.get())
+        })
+    }
+
+    fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// Executable target, in addition to those discovered in `src/`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct Bin {
+    pub name: String,
+
+    /// Source containing `main`, defaults to `src/bin/<name>.c`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+}
+
+/// Library target, built from every source except `src/main.c`
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct Lib {
+    /// Library name without the `lib` prefix or extension, defaults to the
+    /// package name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Kinds of library to build
+    #[serde(default = "Lib::default_types")]
+    pub types: Vec<LibType>,
+}
+
+impl Lib {
+    fn default_types() -> Vec<LibType> {
+        vec![LibType::Static]
+    }
+}
+
+impl Default for Lib {
+    fn default() -> Self {
+        Self {
+            name: None,
+            types: Self::default_types(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LibType {
+    /// `lib<name>.a` archive
+    Static,
+    /// `lib<name>.so` shared object, or the platform's equivalent
+    Shared,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Profiles {
+    #[serde(default)]
+    pub debug: ProfileConfig,
+
+    #[serde(default)]
+    pub release: ProfileConfig,
+}
+
+impl Profiles {
+    fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// Settings for a build profile, unset values use the profile's defaults
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ProfileConfig {
+    pub opt_level: Option<OptLevel>,
+
+    /// Include debug info
+    pub debug: Option<bool>,
+
+    /// Preprocessor macros, as `NAME` or `NAME=VALUE`
+    pub defines: Option<Vec<String>>,
+
+    /// Additional compiler flags
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cflags: Vec<String>,
+
+    /// Additional linker flags
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ldflags: Vec<String>,
+}
+
+/// Compiler optimization level, given as a number or `"s"`, `"z"` or `"g"`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "OptLevelValue", into = "OptLevelValue")]
+pub enum OptLevel {
+    O0,
+    O1,
+    O2,
+    O3,
+    Size,
+    MinSize,
+    Debug,
+}
+
+impl OptLevel {
+    /// Compiler flag for this level
+    pub fn flag(self) -> &'static str {
+        match self {
+            Self::O0 => "-O0",
+            Self::O1 => "-O1",
+            Self::O2 => "-O2",
+            Self::O3 => "-O3",
+            Self::Size => "-Os",
+            Self::MinSize => "-Oz",
+            Self::Debug => "-Og",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum OptLevelValue {
+    Number(u8),
+    Name(String),
+}
+
+impl TryFrom<OptLevelValue> for OptLevel {
+    type Error = String;
+
+    fn try_from(value: OptLevelValue) -> Result<Self, Self::Error> {
+        match value {
+            OptLevelValue::Number(0) => Ok(Self::O0),
+            OptLevelValue::Number(1) => Ok(Self::O1),
+            OptLevelValue::Number(2) => Ok(Self::O2),
+            OptLevelValue::Number(3) => Ok(Self::O3),
+            OptLevelValue::Name(name) if name == "s" => Ok(Self::Size),
+            OptLevelValue::Name(name) if name == "z" => Ok(Self::MinSize),
+            OptLevelValue::Name(name) if name == "g" => Ok(Self::Debug),
+            _ => Err("expected opt_level of 0, 1, 2, 3, \"s\", \"z\" or \"g\"".to_owned()),
+        }
+    }
+}
+
+impl From<OptLevel> for OptLevelValue {
+    fn from(level: OptLevel) -> Self {
+        match level {
+            OptLevel::O0 => Self::Number(0),
+            OptLevel::O1 => Self::Number(1),
+            OptLevel::O2 => Self::Number(2),
+            OptLevel::O3 => Self::Number(3),
+            OptLevel::Size => Self::Name("s".to_owned()),
+            OptLevel::MinSize => Self::Name("z".to_owned()),
+            OptLevel::Debug => Self::Name("g".to_owned()),
+        }
+    }
+}
+
 impl Config {
     pub fn init_from_path<P: AsRef<Path>>(
         path: P,
@@ -53,7 +415,6 @@ impl Config {
         Ok(config)
     }
 
-    #[allow(unused)]
     pub fn load_from_dir(dir: &mut PathBuf) -> anyhow::Result<Self> {
         dir.push(CONFIG_PATH);
         let config = Self::load_from_path(dir)?;
@@ -89,6 +450,48 @@ impl Config {
     }
 }
 
+impl Manifest {
+    /// Nearest directory containing a `Crame.toml`, searching `start` and then
+    /// each of its parents
+    pub fn find_root(start: &Path) -> anyhow::Result<PathBuf> {
+        tracing::debug!(?start, "Searching for manifest");
+
+        start
+            .ancestors()
+            .find(|dir| dir.join(CONFIG_PATH).is_file())
+            .map(Path::to_path_buf)
+            .ok_or_else(|| Error::NoProject(start.to_owned()).into())
+    }
+
+    pub fn load_from_dir(dir: &mut PathBuf) -> anyhow::Result<Self> {
+        dir.push(CONFIG_PATH);
+        let manifest = Self::load_from_path(dir)?;
+        dir.pop();
+
+        Ok(manifest)
+    }
+
+    /// Load a workspace manifest if it has a `[workspace]` table, otherwise a
+    /// package config
+    pub fn load_from_path(path: &Path) -> anyhow::Result<Self> {
+        tracing::debug!(?path, "Loading manifest");
+
+        let contents = fs::read(path).with_context(|| Error::ReadFile(path.to_owned()))?;
+        let parse = || -> Result<Self, toml::de::Error> {
+            let value: toml::Value = toml::from_slice(&contents)?;
+
+            if value.get("workspace").is_some() {
+                let manifest: WorkspaceManifest = value.try_into()?;
+                Ok(Self::Workspace(manifest.workspace))
+            } else {
+                Ok(Self::Package(Box::new(value.try_into()?)))
+            }
+        };
+
+        parse().with_context(|| Error::Config(path.to_owned()))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -142,6 +545,243 @@ mod tests {
         assert_config_error(&err);
     }
 
+    #[test]
+    fn load_build_jobs() {
+        let contents = format!("{}\n[build]\njobs = 4\n", GOOD_TOML);
+        let (_dir, config_path) = create_toml_file(contents.as_bytes());
+        let config = Config::load_from_path(&config_path).expect("valid config");
+        assert_eq!(NonZeroUsize::new(4), config.build.jobs);
+    }
+
+    #[test]
+    fn load_build_zero_jobs_error() {
+        let contents = format!("{}\n[build]\njobs = 0\n", GOOD_TOML);
+        let (_dir, config_path) = create_toml_file(contents.as_bytes());
+        let err = Config::load_from_path(&config_path).expect_err("invalid config");
+        assert_config_error(&err);
+    }
+
+    #[test]
+    fn load_build_compiler_settings() {
+        let contents = format!(
+            "{}\n[build]\ncompiler = \"clang\"\nstd = \"c11\"\nwarnings = \"strict\"\n\
+            deny-warnings = true\ninclude_dirs = [\"include\"]\nlib_dirs = [\"vendor/lib\"]\n",
+            GOOD_TOML
+        );
+        let (_dir, config_path) = create_toml_file(contents.as_bytes());
+        let config = Config::load_from_path(&config_path).expect("valid config");
+
+        let build = &config.build;
+        assert_eq!(Some(CompilerProgram::Clang), build.compiler);
+        assert_eq!(Some(CStandard::C11), build.std);
+        assert_eq!(Some(Warnings::Strict), build.warnings);
+        assert!(build.deny_warnings);
+        assert_eq!(vec![PathBuf::from("include")], build.include_dirs);
+        assert_eq!(vec![PathBuf::from("vendor/lib")], build.lib_dirs);
+    }
+
+    #[test]
+    fn load_build_compiler_path() {
+        let contents = format!("{}\n[build]\ncompiler = \"/opt/cc/bin/cc\"\n", GOOD_TOML);
+        let (_dir, config_path) = create_toml_file(contents.as_bytes());
+        let config = Config::load_from_path(&config_path).expect("valid config");
+
+        let expected = CompilerProgram::Path(PathBuf::from("/opt/cc/bin/cc"));
+        assert_eq!(Some(expected), config.build.compiler);
+    }
+
+    #[test]
+    fn load_build_unknown_compiler_error() {
+        assert_invalid_build_setting("compiler = \"msvc\"");
+    }
+
+    #[test]
+    fn load_build_unknown_std_error() {
+        assert_invalid_build_setting("std = \"c23\"");
+    }
+
+    #[test]
+    fn load_build_unknown_warnings_error() {
+        assert_invalid_build_setting("warnings = \"all\"");
+    }
+
+    #[test]
+    fn load_lib_default_types() {
+        let contents = format!("{}\n[lib]\n", GOOD_TOML);
+        let (_dir, config_path) = create_toml_file(contents.as_bytes());
+        let config = Config::load_from_path(&config_path).expect("valid config");
+        assert_eq!(Some(Lib::default()), config.lib);
+    }
+
+    #[test]
+    fn load_lib_types() {
+        let contents = format!(
+            "{}\n[lib]\nname = \"foo\"\ntypes = [\"static\", \"shared\"]\n",
+            GOOD_TOML
+        );
+        let (_dir, config_path) = create_toml_file(contents.as_bytes());
+        let config = Config::load_from_path(&config_path).expect("valid config");
+
+        let lib = config.lib.expect("lib config");
+        assert_eq!(Some("foo"), lib.name.as_deref());
+        assert_eq!(vec![LibType::Static, LibType::Shared], lib.types);
+    }
+
+    #[test]
+    fn load_lib_unknown_type_error() {
+        let contents = format!("{}\n[lib]\ntypes = [\"dylib\"]\n", GOOD_TOML);
+        let (_dir, config_path) = create_toml_file(contents.as_bytes());
+        let err = Config::load_from_path(&config_path).expect_err("invalid config");
+        assert_config_error(&err);
+    }
+
+    #[test]
+    fn load_bins() {
+        let contents = format!(
+            "{}\n[[bin]]\nname = \"server\"\n\n[[bin]]\nname = \"client\"\npath = \"tools/client.c\"\n",
+            GOOD_TOML
+        );
+        let (_dir, config_path) = create_toml_file(contents.as_bytes());
+        let config = Config::load_from_path(&config_path).expect("valid config");
+
+        let expected = vec![
+            Bin {
+                name: "server".to_owned(),
+                path: None,
+            },
+            Bin {
+                name: "client".to_owned(),
+                path: Some(PathBuf::from("tools/client.c")),
+            },
+        ];
+        assert_eq!(expected, config.bins);
+    }
+
+    #[test]
+    fn load_dependencies() {
+        let contents = format!(
+            "{}\n[dependencies]\nmylib = {{ path = \"../mylib\" }}\n",
+            GOOD_TOML
+        );
+        let (_dir, config_path) = create_toml_file(contents.as_bytes());
+        let config = Config::load_from_path(&config_path).expect("valid config");
+
+        let dependency = &config.dependencies["mylib"];
+        assert_eq!(PathBuf::from("../mylib"), dependency.path);
+    }
+
+    #[test]
+    fn find_root_in_parent() {
+        let (dir, _) = create_good_toml_file();
+        let nested = dir.path().join("src").join("bin");
+        fs::create_dir_all(&nested).expect("create dirs");
+
+        let root = Manifest::find_root(&nested).expect("find root");
+
+        assert_eq!(dir.path(), root);
+    }
+
+    #[test]
+    fn find_root_nearest() {
+        let (dir, _) = create_good_toml_file();
+        let member = dir.path().join("member");
+        fs::create_dir(&member).expect("create dir");
+        fs::write(member.join(CONFIG_PATH), GOOD_TOML).expect("write config");
+
+        let root = Manifest::find_root(&member).expect("find root");
+
+        assert_eq!(member, root);
+    }
+
+    #[test]
+    fn find_root_not_found_error() {
+        let (_dir, path) = testdir_and_path();
+
+        let err = Manifest::find_root(&path).expect_err("no project");
+
+        let err = err.downcast_ref::<Error>().expect("crame error");
+        assert!(matches!(err, Error::NoProject(_)));
+    }
+
+    #[test]
+    fn manifest_package() {
+        let (_dir, config_path) = create_good_toml_file();
+        let manifest = Manifest::load_from_path(&config_path).expect("valid manifest");
+
+        match manifest {
+            Manifest::Package(config) => assert_config(&config),
+            Manifest::Workspace(_) => panic!("expected package manifest"),
+        }
+    }
+
+    #[test]
+    fn manifest_workspace() {
+        let contents = "[workspace]\nmembers = [\"mylib\", \"tools\"]\n";
+        let (_dir, config_path) = create_toml_file(contents.as_bytes());
+        let manifest = Manifest::load_from_path(&config_path).expect("valid manifest");
+
+        match manifest {
+            Manifest::Workspace(workspace) => assert_eq!(
+                vec![PathBuf::from("mylib"), PathBuf::from("tools")],
+                workspace.members
+            ),
+            Manifest::Package(_) => panic!("expected workspace manifest"),
+        }
+    }
+
+    #[test]
+    fn manifest_workspace_invalid() {
+        let contents = "[workspace]\nmember = [\"mylib\"]\n";
+        let (_dir, config_path) = create_toml_file(contents.as_bytes());
+        let err = Manifest::load_from_path(&config_path).expect_err("invalid manifest");
+        assert_config_error(&err);
+    }
+
+    #[test]
+    fn load_test_timeout() {
+        let contents = format!("{}\n[test]\ntimeout = 5\n", GOOD_TOML);
+        let (_dir, config_path) = create_toml_file(contents.as_bytes());
+        let config = Config::load_from_path(&config_path).expect("valid config");
+
+        assert_eq!(Duration::from_secs(5), config.test.timeout());
+    }
+
+    #[test]
+    fn default_test_timeout() {
+        assert_eq!(Duration::from_secs(60), Test::default().timeout());
+    }
+
+    #[test]
+    fn load_test_zero_timeout_error() {
+        let contents = format!("{}\n[test]\ntimeout = 0\n", GOOD_TOML);
+        let (_dir, config_path) = create_toml_file(contents.as_bytes());
+        let err = Config::load_from_path(&config_path).expect_err("invalid config");
+        assert_config_error(&err);
+    }
+
+    #[test]
+    fn load_profiles() {
+        let contents = format!(
+            "{}\n[profile.debug]\ndefines = [\"TRACE\"]\n\n[profile.release]\nopt_level = \"s\"\n",
+            GOOD_TOML
+        );
+        let (_dir, config_path) = create_toml_file(contents.as_bytes());
+        let config = Config::load_from_path(&config_path).expect("valid config");
+
+        let debug = &config.profile.debug;
+        assert_eq!(Some(vec!["TRACE".to_owned()]), debug.defines);
+        assert_eq!(None, debug.opt_level);
+        assert_eq!(Some(OptLevel::Size), config.profile.release.opt_level);
+    }
+
+    #[test]
+    fn load_profile_invalid_opt_level_error() {
+        let contents = format!("{}\n[profile.release]\nopt_level = 4\n", GOOD_TOML);
+        let (_dir, config_path) = create_toml_file(contents.as_bytes());
+        let err = Config::load_from_path(&config_path).expect_err("invalid config");
+        assert_config_error(&err);
+    }
+
     #[test]
     fn save_success() {
         let (_dir, path) = create_toml_path();
@@ -176,6 +816,12 @@ build_system = 'just'
                 name: "testname".into(),
                 build_system: BuildSystem::Just,
             },
+            build: Build::default(),
+            profile: Profiles::default(),
+            test: Test::default(),
+            lib: None,
+            bins: Vec::new(),
+            dependencies: BTreeMap::new(),
         }
     }
 
@@ -199,6 +845,13 @@ build_system = 'just'
         (dir, path)
     }
 
+    fn assert_invalid_build_setting(setting: &str) {
+        let contents = format!("{}\n[build]\n{}\n", GOOD_TOML, setting);
+        let (_dir, config_path) = create_toml_file(contents.as_bytes());
+        let err = Config::load_from_path(&config_path).expect_err("invalid config");
+        assert_config_error(&err);
+    }
+
     fn assert_config(config: &Config) {
         let expected = test_config();
         assert_eq!(expected.package.name, config.package.name);
@@ -0,0 +1,200 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Check if `output` is up to date
+///
+/// It must exist, have been built with the same `fingerprint` recorded in
+/// `fingerprint_path`, and be newer than all of its `inputs`. Paths are
+/// relative to `root`.
+pub fn is_fresh(
+    root: &Path,
+    output: &Path,
+    fingerprint_path: &Path,
+    fingerprint: &str,
+    inputs: &[PathBuf],
+) -> bool {
+    let built = match modified(&root.join(output)) {
+        Some(built) => built,
+        None => return false,
+    };
+
+    match fs::read_to_string(root.join(fingerprint_path)) {
+        Ok(previous) if previous == fingerprint => (),
+        _ => return false,
+    }
+
+    inputs
+        .iter()
+        .all(|input| modified(&root.join(input)).is_some_and(|time| time <= built))
+}
+
+/// Record the `fingerprint` an output was built with
+pub fn record(root: &Path, fingerprint_path: &Path, fingerprint: &str) {
+    let path = root.join(fingerprint_path);
+
+    tracing::debug!(?path, "Recording fingerprint");
+
+    // a missing fingerprint only causes a rebuild next time
+    if let Err(err) = fs::write(&path, fingerprint) {
+        tracing::debug!(%err, "Unable to record fingerprint");
+    }
+}
+
+/// Files a compiled object depends on, read from a `-MMD` style depfile
+///
+/// A missing or unreadable depfile results in `None`.
+pub fn read_depfile(root: &Path, depfile: &Path) -> Option<Vec<PathBuf>> {
+    let contents = fs::read_to_string(root.join(depfile)).ok()?;
+    parse_depfile(&contents)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+/// Parse the prerequisites of the first rule in a makefile style depfile
+fn parse_depfile(contents: &str) -> Option<Vec<PathBuf>> {
+    let contents = contents.replace("\\\r\n", " ").replace("\\\n", " ");
+    let rule = contents.lines().next()?;
+
+    // a target may contain a drive letter colon, so find the separator
+    let separator = rule
+        .find(": ")
+        .or_else(|| rule.strip_suffix(':').map(|r| r.len()))?;
+    let prerequisites = &rule[separator + 1..];
+
+    let mut deps = Vec::new();
+    let mut current = String::new();
+    let mut chars = prerequisites.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&' ') => current.push(chars.next().expect("peeked")),
+            '$' if chars.peek() == Some(&'$') => current.push(chars.next().expect("peeked")),
+            c if c.is_whitespace() => {
+                if !current.is_empty() {
+                    deps.push(PathBuf::from(std::mem::take(&mut current)));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+
+    if !current.is_empty() {
+        deps.push(PathBuf::from(current));
+    }
+
+    Some(deps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::test::testdir_and_path;
+
+    use std::time::Duration;
+
+    #[test]
+    fn parse_depfile_continuations() {
+        let contents = "target/obj/src/a.o: src/a.c src/a.h \\\n  lib/b.h\n";
+        let deps = parse_depfile(contents).expect("parse depfile");
+        assert_eq!(paths(&["src/a.c", "src/a.h", "lib/b.h"]), deps);
+    }
+
+    #[test]
+    fn parse_depfile_escapes() {
+        let contents = "C:\\obj\\a.o: src/my\\ file.c src/$$money.h\n";
+        let deps = parse_depfile(contents).expect("parse depfile");
+        assert_eq!(paths(&["src/my file.c", "src/$money.h"]), deps);
+    }
+
+    #[test]
+    fn parse_depfile_invalid() {
+        assert!(parse_depfile("not a rule").is_none());
+    }
+
+    #[test]
+    fn fresh_when_unchanged() {
+        let (_dir, root) = fingerprinted_output("fingerprint");
+        assert!(is_fresh(
+            &root,
+            out(),
+            cmd(),
+            "fingerprint",
+            &paths(&["in"])
+        ));
+    }
+
+    #[test]
+    fn stale_when_fingerprint_changes() {
+        let (_dir, root) = fingerprinted_output("fingerprint");
+        assert!(!is_fresh(&root, out(), cmd(), "changed", &paths(&["in"])));
+    }
+
+    #[test]
+    fn stale_when_input_newer() {
+        let (_dir, root) = fingerprinted_output("fingerprint");
+        let later = SystemTime::now() + Duration::from_secs(60);
+        let input = fs::File::options()
+            .write(true)
+            .open(root.join("in"))
+            .expect("open input");
+        input.set_modified(later).expect("touch input");
+
+        assert!(!is_fresh(
+            &root,
+            out(),
+            cmd(),
+            "fingerprint",
+            &paths(&["in"])
+        ));
+    }
+
+    #[test]
+    fn stale_when_input_missing() {
+        let (_dir, root) = fingerprinted_output("fingerprint");
+        assert!(!is_fresh(
+            &root,
+            out(),
+            cmd(),
+            "fingerprint",
+            &paths(&["gone"])
+        ));
+    }
+
+    #[test]
+    fn stale_when_output_missing() {
+        let (_dir, root) = fingerprinted_output("fingerprint");
+        fs::remove_file(root.join(out())).expect("remove output");
+        assert!(!is_fresh(
+            &root,
+            out(),
+            cmd(),
+            "fingerprint",
+            &paths(&["in"])
+        ));
+    }
+
+    fn fingerprinted_output(fingerprint: &str) -> (tempfile::TempDir, PathBuf) {
+        let (dir, root) = testdir_and_path();
+        fs::write(root.join("in"), "").expect("write input");
+        fs::write(root.join(out()), "").expect("write output");
+        record(&root, cmd(), fingerprint);
+        (dir, root)
+    }
+
+    fn out() -> &'static Path {
+        Path::new("out")
+    }
+
+    fn cmd() -> &'static Path {
+        Path::new("out.cmd")
+    }
+
+    fn paths(paths: &[&str]) -> Vec<PathBuf> {
+        paths.iter().map(PathBuf::from).collect()
+    }
+}
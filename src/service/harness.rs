@@ -0,0 +1,774 @@
+use crate::util::{
+    error::Error,
+    file::{lexical_normalize, relative_path},
+    message,
+};
+
+use anyhow::Context;
+use serde::Serialize;
+
+use std::{
+    fmt, fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus, Output, Stdio},
+    sync::{
+        mpsc::{self, Receiver},
+        Arc, Mutex, PoisonError,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// First line printed by a runner supporting `--list` and `--run <index>`
+const LIST_HEADER: &str = "# crame tests";
+/// Environment variable giving the runner a file to write events to
+const EVENTS_VAR: &str = "CRAME_TEST_EVENTS";
+/// Time to wait for the rest of a test's output once it exited, as processes
+/// it started may keep its pipes open
+const OUTPUT_GRACE: Duration = Duration::from_millis(100);
+
+/// Test declared with `TEST` in a test file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestCase {
+    /// Position of the test in the runner, used to select it
+    pub index: usize,
+    /// Test file, relative to the package root
+    pub file: PathBuf,
+    pub line: u32,
+    pub name: String,
+    /// Timeout set by the test, instead of the configured one
+    pub timeout: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Passed,
+    Failed,
+    /// Killed by a signal, such as `SIGSEGV`
+    Signaled(i32),
+    /// Stopped after running longer than its timeout
+    TimedOut(Duration),
+}
+
+#[derive(Debug)]
+pub struct TestResult {
+    pub case: TestCase,
+    pub outcome: Outcome,
+    /// Output of the test process, stdout followed by stderr
+    pub output: String,
+    pub duration: Duration,
+    /// Assertions that failed, if the runner reports them
+    pub failures: Vec<Failure>,
+}
+
+/// Failed assertion reported by the runner
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    /// File of the assertion, relative to the package root
+    pub file: PathBuf,
+    pub line: u32,
+    pub assertion: String,
+}
+
+/// Results of running a test executable
+#[derive(Debug)]
+pub struct TestRun {
+    /// Result of each selected test, empty for runners which cannot run tests
+    /// separately
+    pub results: Vec<TestResult>,
+    pub passed: bool,
+}
+
+/// Selects tests by name or file path, like `cargo test`
+#[derive(Debug, Default, Clone)]
+pub struct TestFilter {
+    /// Only select tests matching this pattern
+    pub pattern: Option<String>,
+    /// Match the whole name or path, instead of any part of it
+    pub exact: bool,
+    /// Skip tests matching any of these patterns
+    pub skip: Vec<String>,
+}
+
+impl TestFilter {
+    pub fn matches(&self, case: &TestCase) -> bool {
+        let selected = self
+            .pattern
+            .as_ref()
+            .is_none_or(|pattern| self.matches_pattern(pattern, case));
+
+        selected
+            && !self
+                .skip
+                .iter()
+                .any(|skip| self.matches_pattern(skip, case))
+    }
+
+    fn matches_pattern(&self, pattern: &str, case: &TestCase) -> bool {
+        let file = case.file.to_string_lossy();
+
+        if self.exact {
+            case.name == pattern || file == pattern
+        } else {
+            case.name.contains(pattern) || file.contains(pattern)
+        }
+    }
+}
+
+/// Progress of tests printed with `--message-format json`
+///
+/// Paths are absolute, and durations in milliseconds.
+#[derive(Debug, Serialize)]
+#[serde(tag = "reason")]
+enum TestMessage<'a> {
+    #[serde(rename = "test-listed")]
+    Listed {
+        package: &'a str,
+        name: &'a str,
+        file: PathBuf,
+        line: u32,
+    },
+    #[serde(rename = "test-started")]
+    Started {
+        package: &'a str,
+        name: &'a str,
+        file: PathBuf,
+        line: u32,
+    },
+    #[serde(rename = "test-finished")]
+    Finished {
+        package: &'a str,
+        name: &'a str,
+        file: PathBuf,
+        line: u32,
+        outcome: &'static str,
+        /// Why the test failed, other than a failed assertion
+        note: Option<String>,
+        duration_ms: f64,
+        failures: Vec<FailureMessage<'a>>,
+        output: &'a str,
+    },
+}
+
+#[derive(Debug, Serialize)]
+struct FailureMessage<'a> {
+    file: PathBuf,
+    line: u32,
+    assertion: &'a str,
+}
+
+impl<'a> TestMessage<'a> {
+    fn listed(root: &Path, package: &'a str, case: &'a TestCase) -> Self {
+        Self::Listed {
+            package,
+            name: &case.name,
+            file: lexical_normalize(&root.join(&case.file)),
+            line: case.line,
+        }
+    }
+
+    fn started(root: &Path, package: &'a str, case: &'a TestCase) -> Self {
+        Self::Started {
+            package,
+            name: &case.name,
+            file: lexical_normalize(&root.join(&case.file)),
+            line: case.line,
+        }
+    }
+
+    fn finished(root: &Path, package: &'a str, result: &'a TestResult) -> Self {
+        let failures = result
+            .failures
+            .iter()
+            .map(|failure| FailureMessage {
+                file: lexical_normalize(&root.join(&failure.file)),
+                line: failure.line,
+                assertion: &failure.assertion,
+            })
+            .collect();
+
+        Self::Finished {
+            package,
+            name: &result.case.name,
+            file: lexical_normalize(&root.join(&result.case.file)),
+            line: result.case.line,
+            outcome: result.outcome.kind(),
+            note: result.outcome.note(),
+            duration_ms: result.duration.as_secs_f64() * 1000.0,
+            failures,
+            output: &result.output,
+        }
+    }
+}
+
+/// Run each test of the executable `exe` selected by `filter` in its own
+/// process, printing the results to `out`
+///
+/// A crash only fails the test it happened in, and a test is stopped once it
+/// runs longer than its timeout, or `timeout` if it sets none. Runners from
+/// older projects, which do not support listing tests, are run once as a
+/// whole instead.
+pub fn run_tests(
+    root: &Path,
+    exe: &Path,
+    package: &str,
+    timeout: Duration,
+    filter: &TestFilter,
+    out: &mut dyn Write,
+) -> anyhow::Result<TestRun> {
+    let (list, cases) = list_tests(root, exe)?;
+
+    let mut cases = match cases {
+        Some(cases) => cases,
+        None => {
+            tracing::warn!(
+                "`tests/run.c` cannot run tests separately, update it from a new project"
+            );
+            out.write_all(&list.stdout)?;
+            return Ok(TestRun {
+                results: Vec::new(),
+                passed: list.status.success(),
+            });
+        }
+    };
+
+    let total = cases.len();
+    cases.retain(|case| filter.matches(case));
+    let filtered_out = total - cases.len();
+
+    let plural = if cases.len() == 1 { "" } else { "s" };
+    writeln!(out, "running {} test{}", cases.len(), plural)?;
+
+    let mut results = Vec::with_capacity(cases.len());
+    for case in cases {
+        message::emit(&TestMessage::started(root, package, &case));
+        let result = run_test(root, exe, case, timeout)?;
+        message::emit(&TestMessage::finished(root, package, &result));
+
+        writeln!(out, "test {} ... {}", result.case.name, result.outcome)?;
+        results.push(result);
+    }
+
+    let failures: Vec<_> = results
+        .iter()
+        .filter(|result| result.outcome != Outcome::Passed)
+        .collect();
+
+    if !failures.is_empty() {
+        writeln!(out, "\nfailures:")?;
+        for failure in &failures {
+            let case = &failure.case;
+            writeln!(
+                out,
+                "\n---- {} ({}:{}) ----",
+                case.name,
+                case.file.display(),
+                case.line
+            )?;
+            write!(out, "{}", failure.output)?;
+            if !failure.output.is_empty() && !failure.output.ends_with('\n') {
+                writeln!(out)?;
+            }
+            if let Some(note) = failure.outcome.note() {
+                writeln!(out, "{}", note)?;
+            }
+        }
+    }
+
+    write!(
+        out,
+        "\n{} passed, {} failed",
+        results.len() - failures.len(),
+        failures.len()
+    )?;
+    if filtered_out > 0 {
+        write!(out, ", {} filtered out", filtered_out)?;
+    }
+    writeln!(out)?;
+
+    let passed = failures.is_empty();
+    Ok(TestRun { results, passed })
+}
+
+/// Print the tests of the executable `exe` selected by `filter`, without
+/// running them
+pub fn print_tests(
+    root: &Path,
+    exe: &Path,
+    package: &str,
+    filter: &TestFilter,
+) -> anyhow::Result<()> {
+    for case in test_cases(root, exe)?
+        .iter()
+        .filter(|case| filter.matches(case))
+    {
+        if message::is_json() {
+            message::emit(&TestMessage::listed(root, package, case));
+        } else {
+            println!("{}:{}: {}", case.file.display(), case.line, case.name);
+        }
+    }
+
+    Ok(())
+}
+
+/// Tests of the executable `exe`, failing for runners which cannot list them
+pub fn test_cases(root: &Path, exe: &Path) -> anyhow::Result<Vec<TestCase>> {
+    match list_tests(root, exe)? {
+        (_, Some(cases)) => Ok(cases),
+        (_, None) => {
+            anyhow::bail!("`tests/run.c` cannot list tests, update it from a new project")
+        }
+    }
+}
+
+/// Run `exe --list`, returning its output and the tests if it supports listing
+fn list_tests(root: &Path, exe: &Path) -> anyhow::Result<(Output, Option<Vec<TestCase>>)> {
+    let output = Command::new(root.join(exe))
+        .arg("--list")
+        .current_dir(root)
+        .output()
+        .with_context(|| Error::Execute(exe.to_owned()))?;
+
+    let cases = parse_list(root, &String::from_utf8_lossy(&output.stdout));
+
+    Ok((output, cases))
+}
+
+/// Run the test `case` alone in a new process
+///
+/// The process is killed if it runs longer than the test's timeout, or
+/// `timeout` if it sets none.
+pub fn run_test(
+    root: &Path,
+    exe: &Path,
+    case: TestCase,
+    timeout: Duration,
+) -> anyhow::Result<TestResult> {
+    tracing::debug!(?case, "Running test");
+
+    let events = events_path(root, exe);
+    remove_events(&events)?;
+
+    let timeout = case.timeout.unwrap_or(timeout);
+    let start = Instant::now();
+    let mut child = Command::new(root.join(exe))
+        .arg("--run")
+        .arg(case.index.to_string())
+        .env(EVENTS_VAR, &events)
+        .current_dir(root)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| Error::Execute(exe.to_owned()))?;
+
+    // read while waiting, so a test writing more than a pipe holds can finish
+    let stdout = PipeReader::spawn(child.stdout.take());
+    let stderr = PipeReader::spawn(child.stderr.take());
+
+    let outcome = match wait_timeout(&mut child, timeout)? {
+        Some(status) => Outcome::from_status(&status),
+        None => {
+            child.kill()?;
+            child.wait()?;
+            Outcome::TimedOut(timeout)
+        }
+    };
+    let duration = start.elapsed();
+
+    let deadline = Instant::now() + OUTPUT_GRACE;
+    let mut output = stdout.finish(deadline);
+    output += &stderr.finish(deadline);
+
+    let failures = match fs::read_to_string(&events) {
+        Ok(contents) => parse_events(root, &contents),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(err) => return Err(err).with_context(|| Error::ReadFile(events.clone())),
+    };
+    remove_events(&events)?;
+
+    Ok(TestResult {
+        outcome,
+        output,
+        duration,
+        failures,
+        case,
+    })
+}
+
+/// File the runner reports events of a test to, next to the executable
+fn events_path(root: &Path, exe: &Path) -> PathBuf {
+    let mut path = root.join(exe).into_os_string();
+    path.push(".events");
+    path.into()
+}
+
+fn remove_events(path: &Path) -> anyhow::Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => {
+            Err(err).with_context(|| Error::WriteFile(path.to_owned()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Exit status of `child`, or `None` if it is still running after `timeout`
+fn wait_timeout(child: &mut Child, timeout: Duration) -> io::Result<Option<ExitStatus>> {
+    const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+    let start = Instant::now();
+
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if start.elapsed() >= timeout {
+            return Ok(None);
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Output of a pipe, collected by a background thread
+struct PipeReader {
+    bytes: Arc<Mutex<Vec<u8>>>,
+    /// Disconnected once the pipe is closed
+    closed: Receiver<()>,
+}
+
+impl PipeReader {
+    fn spawn<R: Read + Send + 'static>(reader: Option<R>) -> Self {
+        let bytes = Arc::new(Mutex::new(Vec::new()));
+        let (open, closed) = mpsc::channel();
+
+        let shared = Arc::clone(&bytes);
+        thread::spawn(move || {
+            let _open = open;
+            let mut reader = match reader {
+                Some(reader) => reader,
+                None => return,
+            };
+
+            let mut buf = [0; 8192];
+            loop {
+                match reader.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(len) => shared
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .extend_from_slice(&buf[..len]),
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                    Err(_) => break,
+                }
+            }
+        });
+
+        Self { bytes, closed }
+    }
+
+    /// Output read until the pipe closed, or until `deadline` if it is held
+    /// open by another process
+    fn finish(self, deadline: Instant) -> String {
+        let _ = self
+            .closed
+            .recv_timeout(deadline.saturating_duration_since(Instant::now()));
+
+        let bytes = self.bytes.lock().unwrap_or_else(PoisonError::into_inner);
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+}
+
+/// Tests printed by `--list`, or `None` if the runner does not support it
+///
+/// Each line has the index, file, line, timeout in seconds and name of a test,
+/// separated by tabs. Files are given as the compiler saw them, and made
+/// relative to `root`. A timeout of 0 means the test did not set one.
+fn parse_list(root: &Path, stdout: &str) -> Option<Vec<TestCase>> {
+    let mut lines = stdout.lines();
+
+    if lines.next() != Some(LIST_HEADER) {
+        return None;
+    }
+
+    lines
+        .map(|line| {
+            let mut fields = line.splitn(5, '\t');
+
+            Some(TestCase {
+                index: fields.next()?.parse().ok()?,
+                file: relative_path(root, &root.join(fields.next()?)),
+                line: fields.next()?.parse().ok()?,
+                timeout: parse_timeout(fields.next()?)?,
+                name: fields.next()?.to_owned(),
+            })
+        })
+        .collect()
+}
+
+/// Failed assertions in the events written by the runner
+///
+/// Each line has the kind of event and its fields, separated by tabs. A
+/// `fail` event has the file, line and text of the assertion. Other events
+/// are ignored, so runners can add more.
+fn parse_events(root: &Path, contents: &str) -> Vec<Failure> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(4, '\t');
+            if fields.next()? != "fail" {
+                return None;
+            }
+
+            Some(Failure {
+                file: relative_path(root, &root.join(fields.next()?)),
+                line: fields.next()?.parse().ok()?,
+                assertion: fields.next()?.to_owned(),
+            })
+        })
+        .collect()
+}
+
+/// Timeout in seconds, `Some(None)` for 0, or `None` if invalid
+fn parse_timeout(secs: &str) -> Option<Option<Duration>> {
+    let secs: f64 = secs.parse().ok()?;
+
+    if secs == 0.0 {
+        return Some(None);
+    }
+
+    Duration::try_from_secs_f64(secs).ok().map(Some)
+}
+
+impl Outcome {
+    /// Name of the outcome in messages
+    pub fn kind(self) -> &'static str {
+        match self {
+            Self::Passed => "passed",
+            Self::Failed => "failed",
+            Self::Signaled(_) => "crashed",
+            Self::TimedOut(_) => "timed-out",
+        }
+    }
+
+    /// Why the test failed, other than a failed assertion
+    pub fn note(self) -> Option<String> {
+        match self {
+            Self::Signaled(signal) => Some(format!("killed by signal {}", signal_name(signal))),
+            Self::TimedOut(timeout) => Some(format!("timed out after {}s", timeout.as_secs_f64())),
+            Self::Passed | Self::Failed => None,
+        }
+    }
+
+    fn from_status(status: &ExitStatus) -> Self {
+        if status.success() {
+            return Self::Passed;
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+
+            if let Some(signal) = status.signal() {
+                return Self::Signaled(signal);
+            }
+        }
+
+        Self::Failed
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Passed => write!(f, "ok"),
+            Self::Failed => write!(f, "FAILED"),
+            Self::Signaled(signal) => write!(f, "FAILED ({})", signal_name(*signal)),
+            Self::TimedOut(timeout) => {
+                write!(f, "FAILED (timed out after {}s)", timeout.as_secs_f64())
+            }
+        }
+    }
+}
+
+/// Name of the signals with the same number on every unix platform
+fn signal_name(signal: i32) -> String {
+    let name = match signal {
+        4 => "SIGILL",
+        6 => "SIGABRT",
+        8 => "SIGFPE",
+        9 => "SIGKILL",
+        11 => "SIGSEGV",
+        15 => "SIGTERM",
+        _ => return format!("signal {}", signal),
+    };
+
+    name.to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_list_cases() {
+        let root = Path::new("/ws/tools");
+        let stdout = "# crame tests\n\
+            0\ttarget/debug/obj/../../../tests/unit/a.c\t4\t0\tadds\n\
+            1\t../target/debug/obj/tools/../../../../tools/tests/unit/b.c\t10\t0.5\tname\twith tab\n";
+
+        let cases = parse_list(root, stdout).expect("valid list");
+
+        let expected = vec![
+            TestCase {
+                index: 0,
+                file: PathBuf::from("tests/unit/a.c"),
+                line: 4,
+                name: "adds".to_owned(),
+                timeout: None,
+            },
+            TestCase {
+                index: 1,
+                file: PathBuf::from("tests/unit/b.c"),
+                line: 10,
+                name: "name\twith tab".to_owned(),
+                timeout: Some(Duration::from_millis(500)),
+            },
+        ];
+        assert_eq!(expected, cases);
+    }
+
+    #[test]
+    fn parse_list_without_header() {
+        assert_eq!(
+            None,
+            parse_list(Path::new("/p"), "....\n2 passed, 0 failed\n")
+        );
+    }
+
+    #[test]
+    fn parse_list_invalid_timeout() {
+        let stdout = "# crame tests\n0\ttests/unit/a.c\t4\t-1\tadds\n";
+        assert_eq!(None, parse_list(Path::new("/p"), stdout));
+    }
+
+    #[test]
+    fn parse_events_failures() {
+        let root = Path::new("/p");
+        let contents = "fail\ttarget/debug/obj/../../../tests/unit/a.c\t7\tn + 1 == 5\n\
+            skip\tunknown event\n\
+            fail\ttests/unit/a.c\tnot a line\tx\n";
+
+        let failures = parse_events(root, contents);
+
+        let expected = vec![Failure {
+            file: PathBuf::from("tests/unit/a.c"),
+            line: 7,
+            assertion: "n + 1 == 5".to_owned(),
+        }];
+        assert_eq!(expected, failures);
+    }
+
+    #[test]
+    fn test_finished_message() {
+        let result = TestResult {
+            case: test_case("tests/unit/a.c", "adds"),
+            outcome: Outcome::Failed,
+            output: ".!\n".to_owned(),
+            duration: Duration::from_millis(5),
+            failures: vec![Failure {
+                file: PathBuf::from("tests/unit/a.c"),
+                line: 3,
+                assertion: "1 == 2".to_owned(),
+            }],
+        };
+
+        let message = TestMessage::finished(Path::new("/p"), "calc", &result);
+        let json = serde_json::to_value(message).expect("serialize");
+
+        let expected = serde_json::json!({
+            "reason": "test-finished",
+            "package": "calc",
+            "name": "adds",
+            "file": "/p/tests/unit/a.c",
+            "line": 1,
+            "outcome": "failed",
+            "note": null,
+            "duration_ms": 5.0,
+            "failures": [{"file": "/p/tests/unit/a.c", "line": 3, "assertion": "1 == 2"}],
+            "output": ".!\n",
+        });
+        assert_eq!(expected, json);
+    }
+
+    #[test]
+    fn filter_matches_name_or_file() {
+        let case = test_case("tests/unit/str.c", "strlen counts bytes");
+        let filter = |pattern: &str| TestFilter {
+            pattern: Some(pattern.to_owned()),
+            ..TestFilter::default()
+        };
+
+        assert!(TestFilter::default().matches(&case));
+        assert!(filter("strlen").matches(&case));
+        assert!(filter("unit/str").matches(&case));
+        assert!(!filter("strcmp").matches(&case));
+    }
+
+    #[test]
+    fn filter_exact() {
+        let case = test_case("tests/unit/str.c", "strlen counts bytes");
+        let filter = |pattern: &str| TestFilter {
+            pattern: Some(pattern.to_owned()),
+            exact: true,
+            ..TestFilter::default()
+        };
+
+        assert!(filter("strlen counts bytes").matches(&case));
+        assert!(filter("tests/unit/str.c").matches(&case));
+        assert!(!filter("strlen").matches(&case));
+    }
+
+    #[test]
+    fn filter_skip() {
+        let case = test_case("tests/unit/str.c", "strlen counts bytes");
+        let filter = TestFilter {
+            pattern: Some("str".to_owned()),
+            skip: vec!["slow".to_owned(), "counts".to_owned()],
+            ..TestFilter::default()
+        };
+
+        assert!(!filter.matches(&case));
+    }
+
+    #[test]
+    fn outcome_display() {
+        assert_eq!("ok", Outcome::Passed.to_string());
+        assert_eq!("FAILED (SIGSEGV)", Outcome::Signaled(11).to_string());
+        assert_eq!("FAILED (signal 64)", Outcome::Signaled(64).to_string());
+        assert_eq!(
+            "FAILED (timed out after 1.5s)",
+            Outcome::TimedOut(Duration::from_millis(1500)).to_string()
+        );
+    }
+
+    fn test_case(file: &str, name: &str) -> TestCase {
+        TestCase {
+            index: 0,
+            file: PathBuf::from(file),
+            line: 1,
+            name: name.to_owned(),
+            timeout: None,
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn outcome_from_signal() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let outcome = Outcome::from_status(&ExitStatus::from_raw(6));
+        assert_eq!(Outcome::Signaled(6), outcome);
+    }
+}
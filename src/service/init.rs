@@ -1,6 +1,7 @@
 use super::{
     build_system::{init_build_system, BuildSystem},
-    config::Config,
+    config::{Config, Lib, CONFIG_PATH},
+    template::{Placeholders, Template},
     vcs::{init_vcs, VersionControl},
 };
 use crate::util::error::Error;
@@ -11,28 +12,92 @@ use std::{
     fs,
     io::Write,
     path::{Path, PathBuf},
+    slice,
 };
 
+/// Kind of project to create
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectKind {
+    /// Executable built from `src/main.c`
+    #[default]
+    Bin,
+    /// Library built from `src/lib.c`, with a public header in `include/<name>`
+    Lib,
+}
+
+impl ProjectKind {
+    pub fn from_lib_flag(lib: bool) -> Self {
+        if lib {
+            Self::Lib
+        } else {
+            Self::Bin
+        }
+    }
+
+    fn source_files(self) -> &'static [TemplateFile] {
+        match self {
+            Self::Bin => BIN_SOURCE_FILES,
+            Self::Lib => LIB_SOURCE_FILES,
+        }
+    }
+
+    fn directories(self, name: &str) -> Vec<String> {
+        let mut dirs: Vec<String> = PROJECT_DIRS.iter().map(|&dir| dir.to_owned()).collect();
+        if self == Self::Lib {
+            dirs.push(INCLUDE_DIR.to_owned());
+            dirs.push(format!("{}/{}", INCLUDE_DIR, name));
+        }
+        dirs
+    }
+}
+
 /// Initialize project
 ///
 /// Uses an owned [`PathBuf`] to reduce addition allocations joining paths.
+/// With a `template`, its files replace the built-in sources, and take
+/// precedence over the files crame would otherwise create. Its `Crame.toml`
+/// is the base of the project's config. Placeholders are replaced in the
+/// built-in files as in those of the template.
 #[tracing::instrument(level = "debug")]
 pub fn project_init(
     mut path: PathBuf,
     build_system: BuildSystem,
     vcs: VersionControl,
+    kind: ProjectKind,
+    template: Option<&Template>,
 ) -> anyhow::Result<()> {
+    let mut config = Config::init_from_path(&path, build_system)?;
+    let name = config.package.name.clone();
     let depth = path_depth(&path);
 
-    create_directories(&mut path, depth)?;
+    if let Some(template) = template {
+        if template.contains(CONFIG_PATH) {
+            let package = config.package;
+            config = Config::load_from_path(&template.dir().join(CONFIG_PATH))?;
+            config.package = package;
+        }
+    }
+    let placeholders = Placeholders::new(&config);
 
-    create_program_files(&mut path, depth)?;
+    if let Some(template) = template {
+        template.copy_into(&path, &placeholders)?;
+    }
+
+    create_directories(&mut path, depth, kind, &name, template)?;
+
+    create_program_files(&mut path, depth, kind, &placeholders, template)?;
 
     init_vcs(vcs, &mut path)?;
 
     init_build_system(build_system, &mut path)?;
 
-    let config = Config::init_from_path(&path, build_system)?;
+    if kind == ProjectKind::Lib {
+        config.lib.get_or_insert_with(Lib::default);
+        let include_dir = PathBuf::from(INCLUDE_DIR);
+        if !config.build.include_dirs.contains(&include_dir) {
+            config.build.include_dirs.push(include_dir);
+        }
+    }
     config.save_in_dir(&mut path)?;
 
     tracing::info!("Created new crame project: `{}`", config.package.name);
@@ -41,33 +106,75 @@ pub fn project_init(
 }
 
 const PROJECT_DIRS: &[&str] = &["src", "lib", "tests", "tests/unit"];
+const INCLUDE_DIR: &str = "include";
 
 struct TemplateFile {
     path: &'static str,
-    contents: &'static [u8],
+    contents: &'static str,
 }
 
-const PROJECT_SOURCE_FILES: &[TemplateFile] = &[
+const RUNNER_FILE: TemplateFile = TemplateFile {
+    path: "tests/run.c",
+    contents: include_str!("../../template/tests/run.c"),
+};
+
+const BIN_SOURCE_FILES: &[TemplateFile] = &[
     TemplateFile {
         path: "src/main.c",
-        contents: include_bytes!("../../template/src/main.c"),
+        contents: include_str!("../../template/src/main.c"),
+    },
+    RUNNER_FILE,
+    TemplateFile {
+        path: "tests/unit/it_works.c",
+        contents: include_str!("../../template/tests/unit/it_works.c"),
     },
+];
+
+/// Sources of a library, declaring its functions in `include/<name>/<name>.h`,
+/// prefixed with the name as a C identifier
+const LIB_SOURCE_FILES: &[TemplateFile] = &[
     TemplateFile {
-        path: "tests/run.c",
-        contents: include_bytes!("../../template/tests/run.c"),
+        path: "src/lib.c",
+        contents: include_str!("../../template/lib/src/lib.c"),
     },
     TemplateFile {
-        path: "tests/test_all.c",
-        contents: include_bytes!("../../template/tests/test_all.c"),
+        path: "include/{{name}}/{{name}}.h",
+        contents: include_str!("../../template/lib/include/lib.h"),
     },
+    RUNNER_FILE,
     TemplateFile {
         path: "tests/unit/it_works.c",
-        contents: include_bytes!("../../template/tests/unit/it_works.c"),
+        contents: include_str!("../../template/lib/tests/unit/it_works.c"),
     },
 ];
 
-fn create_directories(path: &mut PathBuf, depth: usize) -> anyhow::Result<()> {
-    for dir in PROJECT_DIRS {
+/// `name` with every character not allowed in a C identifier replaced by `_`
+pub fn c_identifier(name: &str) -> String {
+    let mut ident: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+
+    if !ident.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        ident.insert(0, '_');
+    }
+
+    ident
+}
+
+fn create_directories(
+    path: &mut PathBuf,
+    depth: usize,
+    kind: ProjectKind,
+    name: &str,
+    template: Option<&Template>,
+) -> anyhow::Result<()> {
+    for dir in kind.directories(name) {
+        // already copied from the template
+        if template.is_some() && path.join(&dir).is_dir() {
+            continue;
+        }
+
         path.push(dir);
         let new_depth = path_depth(path);
 
@@ -81,9 +188,22 @@ fn create_directories(path: &mut PathBuf, depth: usize) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn create_program_files(path: &mut PathBuf, depth: usize) -> anyhow::Result<()> {
-    for source in PROJECT_SOURCE_FILES {
-        path.push(source.path);
+fn create_program_files(
+    path: &mut PathBuf,
+    depth: usize,
+    kind: ProjectKind,
+    placeholders: &Placeholders,
+    template: Option<&Template>,
+) -> anyhow::Result<()> {
+    // a template only lacks the runner, which `crame test` needs
+    let sources: &[TemplateFile] = match template {
+        Some(template) if template.contains(RUNNER_FILE.path) => &[],
+        Some(_) => slice::from_ref(&RUNNER_FILE),
+        None => kind.source_files(),
+    };
+
+    for source in sources {
+        path.push(placeholders.render(source.path));
         let new_depth = path_depth(path);
 
         tracing::debug!(?path, "Adding source file");
@@ -91,7 +211,7 @@ fn create_program_files(path: &mut PathBuf, depth: usize) -> anyhow::Result<()>
         let mut dest =
             fs::File::create(&path).with_context(|| Error::CreateFile(path.to_owned()))?;
 
-        dest.write_all(source.contents)
+        dest.write_all(placeholders.render(source.contents).as_bytes())
             .with_context(|| Error::WriteFile(path.to_owned()))?;
 
         remove_path_depth(path, new_depth - depth);
@@ -115,6 +235,8 @@ mod tests {
     use super::*;
     use crate::util::test::{template_dir, testdir_and_path};
 
+    use std::num::NonZeroUsize;
+
     #[test]
     fn directories_exist() {
         let (_dir, dir_path) = testdir_and_path();
@@ -125,8 +247,14 @@ mod tests {
             dir_path.join("tests").join("unit"),
         ];
 
-        project_init(dir_path, BuildSystem::Just, VersionControl::Git)
-            .expect("no error in project_init");
+        project_init(
+            dir_path,
+            BuildSystem::Just,
+            VersionControl::Git,
+            ProjectKind::Bin,
+            None,
+        )
+        .expect("no error in project_init");
 
         for dir in expected_dirs {
             assert!(dir.exists(), "directory should exist: `{}`", dir.display());
@@ -138,8 +266,14 @@ mod tests {
         let (_dir, dir_path) = testdir_and_path();
         let expected_files = program_file_paths(&dir_path);
 
-        project_init(dir_path, BuildSystem::Just, VersionControl::Git)
-            .expect("no error in project_init");
+        project_init(
+            dir_path,
+            BuildSystem::Just,
+            VersionControl::Git,
+            ProjectKind::Bin,
+            None,
+        )
+        .expect("no error in project_init");
 
         for file in expected_files {
             assert!(file.exists(), "file should exist: `{}`", file.display());
@@ -154,21 +288,125 @@ mod tests {
         let template_dir = template_dir();
         let template_files = program_file_paths(&template_dir);
 
-        project_init(tmp_dir_path, BuildSystem::Just, VersionControl::Git)
-            .expect("no error in project_init");
-
+        project_init(
+            tmp_dir_path.clone(),
+            BuildSystem::Just,
+            VersionControl::Git,
+            ProjectKind::Bin,
+            None,
+        )
+        .expect("no error in project_init");
+
+        let config = Config::load_from_dir(&mut tmp_dir_path.clone()).expect("load config");
+        let placeholders = Placeholders::new(&config);
         for (template, created) in template_files.iter().zip(tmp_files.iter()) {
-            let template_contents = file_contents(template);
+            let template_contents = placeholders.render(&file_contents(template));
             let created_contents = file_contents(created);
             assert_eq!(template_contents, created_contents);
         }
     }
 
+    #[test]
+    fn lib_files_exist() {
+        let (_dir, dir_path) = testdir_and_path();
+        let name = dir_path.file_name().unwrap().to_str().unwrap().to_owned();
+        let expected_files = &[
+            dir_path.join("src").join("lib.c"),
+            dir_path
+                .join("include")
+                .join(&name)
+                .join(format!("{}.h", name)),
+            dir_path.join("tests").join("run.c"),
+            dir_path.join("tests").join("unit").join("it_works.c"),
+        ];
+
+        project_init(
+            dir_path.clone(),
+            BuildSystem::Just,
+            VersionControl::None,
+            ProjectKind::Lib,
+            None,
+        )
+        .expect("no error in project_init");
+
+        for file in expected_files {
+            assert!(file.exists(), "file should exist: `{}`", file.display());
+        }
+        assert!(!dir_path.join("src").join("main.c").exists());
+
+        let config = Config::load_from_dir(&mut dir_path.clone()).expect("load config");
+        assert_eq!(Some(Lib::default()), config.lib);
+        assert_eq!(vec![PathBuf::from("include")], config.build.include_dirs);
+    }
+
+    #[test]
+    fn template_files_replace_sources() {
+        let (_template_dir, template) = testdir_and_path();
+        fs::create_dir_all(template.join("src")).expect("create src");
+        fs::create_dir_all(template.join("tests").join("unit")).expect("create empty dir");
+        fs::write(template.join("src").join("app.c"), "int main(void) {}\n").expect("write app");
+        fs::write(template.join("justfile"), "build:\n").expect("write justfile");
+        fs::write(
+            template.join(CONFIG_PATH),
+            "[package]\nname = \"skeleton\"\nbuild_system = \"just\"\n[build]\njobs = 2\n",
+        )
+        .expect("write config");
+        let template = Template::load(template.to_str().unwrap()).expect("load template");
+        let (_dir, dir_path) = testdir_and_path();
+
+        project_init(
+            dir_path.clone(),
+            BuildSystem::Just,
+            VersionControl::None,
+            ProjectKind::Bin,
+            Some(&template),
+        )
+        .expect("no error in project_init");
+
+        assert!(dir_path.join("src").join("app.c").exists());
+        assert!(dir_path.join("tests").join("run.c").exists());
+        assert!(!dir_path.join("src").join("main.c").exists());
+        assert!(!dir_path
+            .join("tests")
+            .join("unit")
+            .join("it_works.c")
+            .exists());
+        assert_eq!("build:\n", file_contents(&dir_path.join("justfile")));
+
+        let config = Config::load_from_dir(&mut dir_path.clone()).expect("load config");
+        let name = dir_path.file_name().unwrap().to_str().unwrap();
+        assert_eq!(name, config.package.name);
+        assert_eq!(NonZeroUsize::new(2), config.build.jobs);
+    }
+
+    #[test]
+    fn lib_files_replace_placeholders() {
+        let (_dir, dir_path) = testdir_and_path();
+        let dir_path = dir_path.join("2d-lib");
+        fs::create_dir(&dir_path).expect("create project dir");
+
+        project_init(
+            dir_path.clone(),
+            BuildSystem::Just,
+            VersionControl::None,
+            ProjectKind::Lib,
+            None,
+        )
+        .expect("no error in project_init");
+
+        let header = file_contents(&dir_path.join("include").join("2d-lib").join("2d-lib.h"));
+        assert!(header.starts_with("#ifndef _2D_LIB_H\n#define _2D_LIB_H\n"));
+        assert!(header.contains("int _2d_lib_add(int a, int b);"));
+        let source = file_contents(&dir_path.join("src").join("lib.c"));
+        assert!(source.starts_with("#include \"2d-lib/2d-lib.h\"\n"));
+        let test = file_contents(&dir_path.join("tests").join("unit").join("it_works.c"));
+        assert!(test.contains("ASSERT_EQ(_2d_lib_add(2, 3), 5);"));
+    }
+
     fn program_file_paths(dir: &Path) -> Vec<PathBuf> {
         vec![
             dir.join("src").join("main.c"),
             dir.join("tests").join("run.c"),
-            dir.join("tests").join("test_all.c"),
             dir.join("tests").join("unit").join("it_works.c"),
         ]
     }
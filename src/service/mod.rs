@@ -1,4 +1,17 @@
+pub mod build;
 pub mod build_system;
+pub mod compdb;
+pub mod compiler;
 pub mod config;
+pub mod fingerprint;
+pub mod harness;
 pub mod init;
+pub mod module;
+pub mod profile;
+pub mod report;
+pub mod run;
+pub mod template;
+pub mod test;
 pub mod vcs;
+pub mod watch;
+pub mod workspace;
@@ -0,0 +1,618 @@
+use super::{
+    config::Config,
+    init::c_identifier,
+    template::Placeholders,
+    test::{include_path, LEGACY_AGGREGATE_FILE, UNIT_DIR},
+};
+use crate::util::{
+    error::Error,
+    file::{find_files, lexical_normalize, relative_path},
+};
+
+use anyhow::Context;
+
+use std::{
+    fs,
+    io::Write,
+    ops::Range,
+    path::{Component, Path, PathBuf},
+};
+
+const SOURCE_DIR: &str = "src";
+/// Directories of a package whose files may include a module
+const INCLUDING_DIRS: &[&str] = &["src", "lib", "include", "tests"];
+
+const SOURCE_TEMPLATE: &str = include_str!("../../template/module/module.c");
+const HEADER_TEMPLATE: &str = include_str!("../../template/module/module.h");
+const TEST_TEMPLATE: &str = include_str!("../../template/module/test.c");
+
+/// Source file and header in `src`, with its unit tests in `tests/unit`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Module {
+    /// Path within `src`, without an extension, such as `util/str`
+    path: PathBuf,
+}
+
+impl Module {
+    /// Module at `path` within `src`, ignoring a `.c` or `.h` extension
+    pub fn new(path: &Path) -> anyhow::Result<Self> {
+        let is_relative = path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        if !is_relative || path.file_name().is_none() {
+            anyhow::bail!(Error::InvalidModule(path.to_owned()));
+        }
+
+        let path = match path.extension() {
+            Some(ext) if ext == "c" || ext == "h" => path.with_extension(""),
+            _ => path.to_owned(),
+        };
+
+        Ok(Self { path })
+    }
+
+    /// Source file, relative to the package root
+    pub fn source(&self) -> PathBuf {
+        Path::new(SOURCE_DIR).join(self.file("c"))
+    }
+
+    /// Header file, relative to the package root
+    pub fn header(&self) -> PathBuf {
+        Path::new(SOURCE_DIR).join(self.file("h"))
+    }
+
+    /// Unit test file, relative to the package root
+    pub fn test(&self) -> PathBuf {
+        Path::new(UNIT_DIR).join(self.file("c"))
+    }
+
+    /// Source, header and unit test files
+    fn files(&self) -> [PathBuf; 3] {
+        [self.source(), self.header(), self.test()]
+    }
+
+    /// Module path with `extension` added, keeping any dots in its name
+    fn file(&self, extension: &str) -> PathBuf {
+        let mut file = self.path.clone().into_os_string();
+        file.push(".");
+        file.push(extension);
+        file.into()
+    }
+
+    /// Include guard of the header, prefixed with the package name
+    fn guard(&self, package: &str) -> String {
+        let path = self
+            .path
+            .iter()
+            .map(|part| c_identifier(&part.to_string_lossy()))
+            .collect::<Vec<_>>()
+            .join("_");
+
+        format!("{}_{}_H", c_identifier(package), path).to_uppercase()
+    }
+
+    /// Header as included from the unit test file
+    fn test_include(&self) -> String {
+        let mut include = String::new();
+        for _ in self.test().iter().skip(1) {
+            include += "../";
+        }
+        include + &include_path(&self.header())
+    }
+}
+
+/// Create the files of `module` in the package at `root` with `config`
+///
+/// Nothing is written if any of them already exists. A hand-written
+/// `tests/test_all.c` has the new test file added to it, as it is used
+/// instead of the discovered tests.
+#[tracing::instrument(level = "debug")]
+pub fn add_module(root: &Path, config: &Config, module: &Module) -> anyhow::Result<()> {
+    let placeholders = Placeholders::new(config);
+    let guard = module.guard(&config.package.name);
+    let header = module.file("h");
+    let header = header.file_name().expect("checked in Module::new");
+    let source = placeholders.with_header(&header.to_string_lossy(), &guard);
+    let test = placeholders.with_header(&module.test_include(), &guard);
+
+    let files = [
+        (module.source(), source.render(SOURCE_TEMPLATE)),
+        (module.header(), source.render(HEADER_TEMPLATE)),
+        (module.test(), test.render(TEST_TEMPLATE)),
+    ];
+
+    for (path, _) in &files {
+        let path = root.join(path);
+        if path.exists() {
+            anyhow::bail!(Error::Conflict(path));
+        }
+    }
+
+    tracing::info!(
+        "Creating module files `{}`, `{}` and `{}`",
+        module.source().display(),
+        module.header().display(),
+        module.test().display()
+    );
+
+    for (path, contents) in &files {
+        create_file(&root.join(path), contents)?;
+    }
+
+    let legacy = root.join(LEGACY_AGGREGATE_FILE);
+    if legacy.exists() {
+        register_test(&legacy, module)?;
+    }
+
+    Ok(())
+}
+
+fn create_file(path: &Path, contents: &str) -> anyhow::Result<()> {
+    let dir = path.parent().expect("module files are within a directory");
+    fs::create_dir_all(dir).with_context(|| Error::CreateDir(dir.to_owned()))?;
+
+    tracing::debug!(?path, "Adding module file");
+
+    fs::write(path, contents).with_context(|| Error::CreateFile(path.to_owned()))
+}
+
+/// Include the test file of `module` in the hand-written aggregate at `path`
+fn register_test(path: &Path, module: &Module) -> anyhow::Result<()> {
+    let contents = fs::read_to_string(path).with_context(|| Error::ReadFile(path.to_owned()))?;
+    let test = module.test();
+    let test = test
+        .strip_prefix("tests")
+        .expect("unit tests are in `tests`");
+
+    let mut line = format!("#include \"{}\"\n", include_path(test));
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        line.insert(0, '\n');
+    }
+
+    tracing::debug!(?path, "Registering test file");
+
+    fs::OpenOptions::new()
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .with_context(|| Error::WriteFile(path.to_owned()))
+}
+
+/// Move the files of module `from` to `to`, in the package at `root` named
+/// `package`
+///
+/// The header's include guard is renamed, and every quoted `#include` of a
+/// moved file in `src`, `lib`, `include` and `tests` is updated, including
+/// those of the moved files themselves.
+#[tracing::instrument(level = "debug")]
+pub fn rename_module(root: &Path, package: &str, from: &Module, to: &Module) -> anyhow::Result<()> {
+    let moves: Vec<_> = from
+        .files()
+        .into_iter()
+        .zip(to.files())
+        .map(|(from, to)| (root.join(from), root.join(to)))
+        .filter(|(from, _)| from.exists())
+        .collect();
+
+    if moves.is_empty() {
+        anyhow::bail!(Error::NoModule(from.path.clone()));
+    }
+    for (_, to) in &moves {
+        if to.exists() {
+            anyhow::bail!(Error::Conflict(to.clone()));
+        }
+    }
+
+    let renames: Vec<_> = moves
+        .iter()
+        .map(|(from, to)| (from.clone(), Some(to.clone())))
+        .collect();
+    let mut updates = Vec::new();
+
+    for file in package_files(root)? {
+        let new_file = moves
+            .iter()
+            .find(|(from, _)| *from == file)
+            .map_or(file.as_path(), |(_, to)| to.as_path());
+
+        let contents = fs::read_to_string(&file).with_context(|| Error::ReadFile(file.clone()))?;
+        let mut updated = rewrite_includes(&contents, &file, new_file, &renames);
+
+        if file == root.join(from.header()) {
+            let guard = replace_identifier(
+                updated.as_deref().unwrap_or(&contents),
+                &from.guard(package),
+                &to.guard(package),
+            );
+            match guard {
+                Some(guard) => updated = Some(guard),
+                None => tracing::warn!(
+                    "No include guard `{}` in `{}` to rename",
+                    from.guard(package),
+                    from.header().display()
+                ),
+            }
+        }
+
+        if updated.is_some() || new_file != file {
+            updates.push((file.clone(), new_file.to_owned(), updated));
+        }
+    }
+
+    for (file, new_file, contents) in updates {
+        if new_file != file {
+            move_file(&file, &new_file)?;
+            remove_empty_dirs(root, file.parent().expect("file within a directory"));
+            tracing::info!(
+                "Renamed `{}` to `{}`",
+                relative_path(root, &file).display(),
+                relative_path(root, &new_file).display()
+            );
+        }
+        if let Some(contents) = contents {
+            fs::write(&new_file, contents).with_context(|| Error::WriteFile(new_file.clone()))?;
+            tracing::info!("Updated `{}`", relative_path(root, &new_file).display());
+        }
+    }
+
+    Ok(())
+}
+
+/// Delete the files of `module` in the package at `root`
+///
+/// Quoted `#include`s of the deleted files are removed from the remaining
+/// files, such as the test registered in a hand-written `tests/test_all.c`.
+#[tracing::instrument(level = "debug")]
+pub fn remove_module(root: &Path, module: &Module) -> anyhow::Result<()> {
+    let removed: Vec<_> = module
+        .files()
+        .iter()
+        .map(|file| root.join(file))
+        .filter(|file| file.exists())
+        .collect();
+
+    if removed.is_empty() {
+        anyhow::bail!(Error::NoModule(module.path.clone()));
+    }
+
+    for file in &removed {
+        fs::remove_file(file).with_context(|| Error::RemoveFile(file.clone()))?;
+        remove_empty_dirs(root, file.parent().expect("file within a directory"));
+        tracing::info!("Removed `{}`", relative_path(root, file).display());
+    }
+
+    let removals: Vec<_> = removed.into_iter().map(|file| (file, None)).collect();
+
+    for file in package_files(root)? {
+        let contents = fs::read_to_string(&file).with_context(|| Error::ReadFile(file.clone()))?;
+
+        if let Some(updated) = rewrite_includes(&contents, &file, &file, &removals) {
+            fs::write(&file, updated).with_context(|| Error::WriteFile(file.clone()))?;
+            tracing::info!(
+                "Removed includes of `{}` from `{}`",
+                module.path.display(),
+                relative_path(root, &file).display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// C sources and headers of a package whose includes may refer to a module
+fn package_files(root: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    for dir in INCLUDING_DIRS {
+        files.extend(find_files(&root.join(dir), "c")?);
+        files.extend(find_files(&root.join(dir), "h")?);
+    }
+
+    Ok(files)
+}
+
+/// Contents of the file moved from `old` to `new` with its quoted includes
+/// updated, or `None` if none changed
+///
+/// Each of `changes` maps an absolute path to its new path, or to `None` if
+/// it was removed, in which case its includes are deleted. Includes of other
+/// existing files are kept pointing at them after the file moves.
+fn rewrite_includes(
+    contents: &str,
+    old: &Path,
+    new: &Path,
+    changes: &[(PathBuf, Option<PathBuf>)],
+) -> Option<String> {
+    let old_dir = old.parent().expect("file within a directory");
+    let new_dir = new.parent().expect("file within a directory");
+    let mut rewritten = String::with_capacity(contents.len());
+    let mut changed = false;
+
+    for line in contents.split_inclusive('\n') {
+        let range = match quoted_include(line) {
+            Some(range) => range,
+            None => {
+                rewritten += line;
+                continue;
+            }
+        };
+
+        let target = lexical_normalize(&old_dir.join(&line[range.clone()]));
+        let target = match changes.iter().find(|(path, _)| *path == target) {
+            Some((_, Some(renamed))) => renamed.clone(),
+            Some((_, None)) => {
+                changed = true;
+                continue;
+            }
+            None if old_dir != new_dir && target.exists() => target,
+            None => {
+                rewritten += line;
+                continue;
+            }
+        };
+
+        let include = include_path(&relative_path(new_dir, &target));
+        changed |= include != line[range.clone()];
+        rewritten += &line[..range.start];
+        rewritten += &include;
+        rewritten += &line[range.end..];
+    }
+
+    changed.then_some(rewritten)
+}
+
+/// Byte range of the path in a `#include "path"` directive
+fn quoted_include(line: &str) -> Option<Range<usize>> {
+    let directive = line.trim_start().strip_prefix('#')?.trim_start();
+    let path = directive.strip_prefix("include")?.trim_start();
+    let path = path.strip_prefix('"')?;
+    let len = path.find('"')?;
+
+    let start = line.len() - path.len();
+    Some(start..start + len)
+}
+
+/// `contents` with every occurrence of the identifier `old` replaced by `new`,
+/// or `None` if there were none
+fn replace_identifier(contents: &str, old: &str, new: &str) -> Option<String> {
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut replaced = String::with_capacity(contents.len());
+    let mut last = 0;
+
+    for (start, _) in contents.match_indices(old) {
+        let end = start + old.len();
+        let before = contents[..start].chars().next_back();
+        let after = contents[end..].chars().next();
+        if before.is_some_and(is_ident) || after.is_some_and(is_ident) {
+            continue;
+        }
+
+        replaced += &contents[last..start];
+        replaced += new;
+        last = end;
+    }
+
+    if last == 0 {
+        return None;
+    }
+    replaced += &contents[last..];
+    Some(replaced)
+}
+
+fn move_file(from: &Path, to: &Path) -> anyhow::Result<()> {
+    let dir = to.parent().expect("file within a directory");
+    fs::create_dir_all(dir).with_context(|| Error::CreateDir(dir.to_owned()))?;
+
+    tracing::debug!(?from, ?to, "Moving module file");
+
+    fs::rename(from, to).with_context(|| Error::CreateFile(to.to_owned()))
+}
+
+/// Remove `dir` and its parents while they are empty, stopping at the
+/// directories a package starts with
+fn remove_empty_dirs(root: &Path, dir: &Path) {
+    let keep = [root.join(SOURCE_DIR), root.join(UNIT_DIR)];
+
+    for dir in dir.ancestors() {
+        if !dir.starts_with(root) || keep.iter().any(|keep| keep.starts_with(dir)) {
+            break;
+        }
+        // only succeeds for empty directories
+        if fs::remove_dir(dir).is_err() {
+            break;
+        }
+        tracing::debug!(?dir, "Removed empty directory");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::test::testdir_project;
+
+    #[test]
+    fn module_paths() {
+        let module = Module::new(Path::new("util/str.c")).expect("valid module");
+
+        assert_eq!(PathBuf::from("src/util/str.c"), module.source());
+        assert_eq!(PathBuf::from("src/util/str.h"), module.header());
+        assert_eq!(PathBuf::from("tests/unit/util/str.c"), module.test());
+        assert_eq!("../../../src/util/str.h", module.test_include());
+        assert_eq!("MY_APP_UTIL_STR_H", module.guard("my-app"));
+    }
+
+    #[test]
+    fn module_outside_src_error() {
+        for path in ["../str", "/str", "util/../str", ""] {
+            let err = Module::new(Path::new(path)).expect_err("invalid module");
+
+            let err = err.downcast_ref::<Error>().expect("crame error");
+            assert!(matches!(err, Error::InvalidModule(_)), "{}", path);
+        }
+    }
+
+    #[test]
+    fn add_module_files() {
+        let (_dir, root) = testdir_project();
+        let module = Module::new(Path::new("util/str")).expect("valid module");
+
+        add_module(&root, &config("my-app"), &module).expect("add module");
+
+        let read = |path: &str| fs::read_to_string(root.join(path)).expect("read module file");
+        assert_eq!("#include \"str.h\"\n", read("src/util/str.c"));
+        assert_eq!(
+            "#ifndef MY_APP_UTIL_STR_H\n#define MY_APP_UTIL_STR_H\n\n#endif\n",
+            read("src/util/str.h")
+        );
+        assert_eq!(
+            "#if defined HEADERS\n#include \"../../../src/util/str.h\"\n#elif defined TESTS\n\n#endif\n",
+            read("tests/unit/util/str.c")
+        );
+    }
+
+    #[test]
+    fn add_module_conflict() {
+        let (_dir, root) = testdir_project();
+        fs::write(root.join("src").join("str.h"), "").expect("write header");
+        let module = Module::new(Path::new("str")).expect("valid module");
+
+        let err = add_module(&root, &config("app"), &module).expect_err("existing header");
+
+        let err = err.downcast_ref::<Error>().expect("crame error");
+        assert!(matches!(err, Error::Conflict(_)));
+        assert!(!root.join("src").join("str.c").exists());
+        assert!(!root.join("tests").join("unit").join("str.c").exists());
+    }
+
+    #[test]
+    fn add_module_registers_legacy_test() {
+        let (_dir, root) = testdir_project();
+        let legacy = root.join(LEGACY_AGGREGATE_FILE);
+        fs::write(&legacy, "#include \"unit/it_works.c\"").expect("write aggregate");
+        let module = Module::new(Path::new("util/str")).expect("valid module");
+
+        add_module(&root, &config("app"), &module).expect("add module");
+
+        assert_eq!(
+            "#include \"unit/it_works.c\"\n#include \"unit/util/str.c\"\n",
+            fs::read_to_string(&legacy).expect("read aggregate")
+        );
+    }
+
+    #[test]
+    fn rename_module_updates_includes_and_guard() {
+        let (_dir, root) = testdir_project();
+        let module = Module::new(Path::new("util/str")).expect("valid module");
+        add_module(&root, &config("app"), &module).expect("add module");
+        fs::write(
+            root.join("src").join("main.c"),
+            "#include <stdio.h>\n#include \"util/str.h\"\n#include \"missing.h\"\n",
+        )
+        .expect("write main");
+        let renamed = Module::new(Path::new("text")).expect("valid module");
+
+        rename_module(&root, "app", &module, &renamed).expect("rename module");
+
+        let read = |path: &str| fs::read_to_string(root.join(path)).expect("read file");
+        assert_eq!("#include \"text.h\"\n", read("src/text.c"));
+        assert_eq!(
+            "#ifndef APP_TEXT_H\n#define APP_TEXT_H\n\n#endif\n",
+            read("src/text.h")
+        );
+        assert_eq!(
+            "#if defined HEADERS\n#include \"../../src/text.h\"\n#elif defined TESTS\n\n#endif\n",
+            read("tests/unit/text.c")
+        );
+        assert_eq!(
+            "#include <stdio.h>\n#include \"text.h\"\n#include \"missing.h\"\n",
+            read("src/main.c")
+        );
+        assert!(!root.join("src").join("util").exists());
+        assert!(!root.join("tests").join("unit").join("util").exists());
+    }
+
+    #[test]
+    fn rename_module_keeps_includes_of_moved_file() {
+        let (_dir, root) = testdir_project();
+        fs::write(root.join("src").join("log.h"), "").expect("write header");
+        fs::write(
+            root.join("src").join("str.c"),
+            "#include \"log.h\"\n#  include \"str.h\" // own header\n",
+        )
+        .expect("write source");
+        fs::write(root.join("src").join("str.h"), "").expect("write header");
+        let from = Module::new(Path::new("str")).expect("valid module");
+        let to = Module::new(Path::new("util/str")).expect("valid module");
+
+        rename_module(&root, "app", &from, &to).expect("rename module");
+
+        assert_eq!(
+            "#include \"../log.h\"\n#  include \"str.h\" // own header\n",
+            fs::read_to_string(root.join("src").join("util").join("str.c")).expect("read source")
+        );
+    }
+
+    #[test]
+    fn rename_module_conflict() {
+        let (_dir, root) = testdir_project();
+        let from = Module::new(Path::new("a")).expect("valid module");
+        let to = Module::new(Path::new("b")).expect("valid module");
+        add_module(&root, &config("app"), &from).expect("add module");
+        add_module(&root, &config("app"), &to).expect("add module");
+
+        let err = rename_module(&root, "app", &from, &to).expect_err("existing module");
+
+        let err = err.downcast_ref::<Error>().expect("crame error");
+        assert!(matches!(err, Error::Conflict(_)));
+        assert!(root.join("src").join("a.c").exists());
+    }
+
+    #[test]
+    fn remove_module_files_and_registration() {
+        let (_dir, root) = testdir_project();
+        let legacy = root.join(LEGACY_AGGREGATE_FILE);
+        fs::write(&legacy, "#include \"unit/it_works.c\"\n").expect("write aggregate");
+        let module = Module::new(Path::new("util/str")).expect("valid module");
+        add_module(&root, &config("app"), &module).expect("add module");
+
+        remove_module(&root, &module).expect("remove module");
+
+        for file in module.files() {
+            assert!(!root.join(&file).exists(), "removed: `{}`", file.display());
+        }
+        assert!(!root.join("src").join("util").exists());
+        assert!(root.join("src").exists());
+        assert_eq!(
+            "#include \"unit/it_works.c\"\n",
+            fs::read_to_string(&legacy).expect("read aggregate")
+        );
+    }
+
+    #[test]
+    fn remove_missing_module_error() {
+        let (_dir, root) = testdir_project();
+        let module = Module::new(Path::new("nothing")).expect("valid module");
+
+        let err = remove_module(&root, &module).expect_err("no module");
+
+        let err = err.downcast_ref::<Error>().expect("crame error");
+        assert!(matches!(err, Error::NoModule(_)));
+    }
+
+    #[test]
+    fn replace_identifier_whole_words() {
+        let contents = "#ifndef A_H\n#define A_H\nint BA_H, A_HX;\n";
+
+        assert_eq!(
+            Some("#ifndef B_H\n#define B_H\nint BA_H, A_HX;\n".to_owned()),
+            replace_identifier(contents, "A_H", "B_H")
+        );
+        assert_eq!(None, replace_identifier(contents, "C_H", "B_H"));
+    }
+
+    fn config(name: &str) -> Config {
+        let mut config = Config::default();
+        config.package.name = name.to_owned();
+        config
+    }
+}
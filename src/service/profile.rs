@@ -0,0 +1,120 @@
+use super::config::{Config, OptLevel, ProfileConfig};
+
+/// Set of build settings, each with its own output directory
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    #[default]
+    Debug,
+    Release,
+}
+
+impl Profile {
+    /// Profile selected by a `--release` flag
+    pub fn from_release_flag(release: bool) -> Self {
+        match release {
+            true => Self::Release,
+            false => Self::Debug,
+        }
+    }
+
+    /// Name of the profile's directory within `target/`
+    pub fn dir_name(self) -> &'static str {
+        match self {
+            Self::Debug => "debug",
+            Self::Release => "release",
+        }
+    }
+
+    /// Flags used when compiling with this profile
+    pub fn cflags(self, config: &Config) -> Vec<String> {
+        let profile = self.config(config);
+        let mut flags = Vec::new();
+
+        let opt_level = profile
+            .opt_level
+            .unwrap_or_else(|| self.default_opt_level());
+        flags.push(opt_level.flag().to_owned());
+
+        if profile.debug.unwrap_or_else(|| self.default_debug()) {
+            flags.push("-g".to_owned());
+        }
+
+        let defines = match &profile.defines {
+            Some(defines) => defines.iter().map(String::as_str).collect(),
+            None => self.default_defines().to_vec(),
+        };
+        flags.extend(defines.iter().map(|define| format!("-D{}", define)));
+
+        flags.extend(profile.cflags.iter().cloned());
+
+        flags
+    }
+
+    /// Flags used when linking with this profile
+    pub fn ldflags(self, config: &Config) -> Vec<String> {
+        self.config(config).ldflags.clone()
+    }
+
+    fn config(self, config: &Config) -> &ProfileConfig {
+        match self {
+            Self::Debug => &config.profile.debug,
+            Self::Release => &config.profile.release,
+        }
+    }
+
+    fn default_opt_level(self) -> OptLevel {
+        match self {
+            Self::Debug => OptLevel::O0,
+            Self::Release => OptLevel::O2,
+        }
+    }
+
+    fn default_debug(self) -> bool {
+        match self {
+            Self::Debug => true,
+            Self::Release => false,
+        }
+    }
+
+    fn default_defines(self) -> &'static [&'static str] {
+        match self {
+            Self::Debug => &[],
+            Self::Release => &["NDEBUG"],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_default_cflags() {
+        let flags = Profile::Debug.cflags(&Config::default());
+        assert_eq!(vec!["-O0", "-g"], flags);
+    }
+
+    #[test]
+    fn release_default_cflags() {
+        let flags = Profile::Release.cflags(&Config::default());
+        assert_eq!(vec!["-O2", "-DNDEBUG"], flags);
+    }
+
+    #[test]
+    fn configured_cflags() {
+        let mut config = Config::default();
+        config.profile.release = ProfileConfig {
+            opt_level: Some(OptLevel::O3),
+            debug: Some(true),
+            defines: Some(vec!["LOG=0".to_owned()]),
+            cflags: vec!["-flto".to_owned()],
+            ldflags: vec!["-flto".to_owned()],
+        };
+
+        let cflags = Profile::Release.cflags(&config);
+        let ldflags = Profile::Release.ldflags(&config);
+
+        assert_eq!(vec!["-O3", "-g", "-DLOG=0", "-flto"], cflags);
+        assert_eq!(vec!["-flto"], ldflags);
+    }
+}
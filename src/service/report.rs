@@ -0,0 +1,344 @@
+use super::harness::{Outcome, TestResult};
+
+use clap::ValueEnum;
+
+use std::{
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+
+/// Machine readable format for test results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    /// JUnit XML, as read by GitLab and Jenkins
+    Junit,
+    /// Test Anything Protocol, version 13
+    Tap,
+}
+
+/// Machine readable report requested for a test run
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub format: ReportFormat,
+    /// File to write the report to, instead of stdout
+    pub output: Option<PathBuf>,
+}
+
+/// Test results of one package
+#[derive(Debug)]
+pub struct Suite {
+    pub name: String,
+    /// Package root, relative to the workspace root
+    pub dir: PathBuf,
+    pub results: Vec<TestResult>,
+}
+
+impl ReportFormat {
+    /// Report of the results of every suite
+    pub fn render(self, suites: &[Suite]) -> String {
+        match self {
+            Self::Junit => junit(suites),
+            Self::Tap => tap(suites),
+        }
+    }
+}
+
+fn junit(suites: &[Suite]) -> String {
+    let results = suites.iter().flat_map(|suite| &suite.results);
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+
+    let _ = writeln!(
+        xml,
+        "<testsuites tests=\"{}\" failures=\"{}\" time=\"{:.3}\">",
+        results.clone().count(),
+        results.clone().filter(|result| is_failure(result)).count(),
+        results
+            .map(|result| result.duration.as_secs_f64())
+            .sum::<f64>(),
+    );
+
+    for suite in suites {
+        let _ = writeln!(
+            xml,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">",
+            xml_attr(&suite.name),
+            suite.results.len(),
+            suite
+                .results
+                .iter()
+                .filter(|result| is_failure(result))
+                .count(),
+            suite
+                .results
+                .iter()
+                .map(|result| result.duration.as_secs_f64())
+                .sum::<f64>(),
+        );
+
+        for result in &suite.results {
+            let case = &result.case;
+            let _ = write!(
+                xml,
+                "    <testcase name=\"{}\" classname=\"{}\" file=\"{}\" line=\"{}\" time=\"{:.3}\"",
+                xml_attr(&case.name),
+                xml_attr(&suite.name),
+                xml_attr(&report_path(&suite.dir, &case.file)),
+                case.line,
+                result.duration.as_secs_f64(),
+            );
+
+            if !is_failure(result) {
+                xml += "/>\n";
+                continue;
+            }
+
+            let _ = writeln!(
+                xml,
+                ">\n      <failure message=\"{}\" type=\"{}\">{}</failure>",
+                xml_attr(&failure_message(suite, result)),
+                failure_type(result.outcome),
+                xml_escape(&result.output),
+            );
+            xml += "    </testcase>\n";
+        }
+
+        xml += "  </testsuite>\n";
+    }
+
+    xml += "</testsuites>\n";
+    xml
+}
+
+fn tap(suites: &[Suite]) -> String {
+    let results: Vec<_> = suites
+        .iter()
+        .flat_map(|suite| suite.results.iter().map(move |result| (suite, result)))
+        .collect();
+
+    let mut tap = format!("TAP version 13\n1..{}\n", results.len());
+
+    for (number, (suite, result)) in results.iter().enumerate() {
+        let case = &result.case;
+        let status = if is_failure(result) { "not ok" } else { "ok" };
+        // `#` starts a directive, such as SKIP, in the description
+        let name = case.name.replace('#', "\\#");
+        let _ = writeln!(tap, "{} {} - {}", status, number + 1, name);
+
+        tap += "  ---\n";
+        let _ = writeln!(
+            tap,
+            "  file: {}",
+            yaml_string(&report_path(&suite.dir, &case.file))
+        );
+        let _ = writeln!(tap, "  line: {}", case.line);
+        let _ = writeln!(
+            tap,
+            "  duration_ms: {:.3}",
+            result.duration.as_secs_f64() * 1000.0
+        );
+
+        if is_failure(result) {
+            let _ = writeln!(
+                tap,
+                "  message: {}",
+                yaml_string(&failure_message(suite, result))
+            );
+            let _ = writeln!(tap, "  severity: {}", failure_type(result.outcome));
+            if !result.output.is_empty() {
+                tap += "  output: |-\n";
+                for line in result.output.lines() {
+                    let _ = writeln!(tap, "    {}", line);
+                }
+            }
+        }
+        tap += "  ...\n";
+    }
+
+    tap
+}
+
+fn is_failure(result: &TestResult) -> bool {
+    result.outcome != Outcome::Passed
+}
+
+/// The failed assertions of `result`, or why it failed without any
+fn failure_message(suite: &Suite, result: &TestResult) -> String {
+    if let Some(note) = result.outcome.note() {
+        return note;
+    }
+
+    if result.failures.is_empty() {
+        return "test failed".to_owned();
+    }
+
+    result
+        .failures
+        .iter()
+        .map(|failure| {
+            format!(
+                "{} at {}:{}",
+                failure.assertion,
+                report_path(&suite.dir, &failure.file),
+                failure.line
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn failure_type(outcome: Outcome) -> &'static str {
+    match outcome {
+        Outcome::Signaled(_) => "crash",
+        Outcome::TimedOut(_) => "timeout",
+        Outcome::Passed | Outcome::Failed => "assertion",
+    }
+}
+
+/// Path of a test file relative to the workspace root, with `/` separators
+fn report_path(dir: &Path, file: &Path) -> String {
+    dir.join(file)
+        .iter()
+        .map(|part| part.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Text escaped for an XML attribute, keeping its line breaks
+fn xml_attr(text: &str) -> String {
+    xml_escape(text).replace('\n', "&#10;")
+}
+
+/// Text escaped for XML content, without characters XML cannot contain
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for char in text.chars() {
+        match char {
+            '&' => escaped += "&amp;",
+            '<' => escaped += "&lt;",
+            '>' => escaped += "&gt;",
+            '"' => escaped += "&quot;",
+            '\'' => escaped += "&apos;",
+            '\t' | '\n' | '\r' => escaped.push(char),
+            char if char < ' ' => escaped.push(char::REPLACEMENT_CHARACTER),
+            char => escaped.push(char),
+        }
+    }
+
+    escaped
+}
+
+/// Single line YAML string in double quotes
+fn yaml_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+
+    for char in text.chars() {
+        match char {
+            '"' => quoted += "\\\"",
+            '\\' => quoted += "\\\\",
+            '\n' => quoted += "\\n",
+            '\t' => quoted += "\\t",
+            char if char < ' ' => {
+                let _ = write!(quoted, "\\x{:02x}", char as u32);
+            }
+            char => quoted.push(char),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::harness::{Failure, TestCase};
+
+    use std::time::Duration;
+
+    #[test]
+    fn junit_report() {
+        let report = ReportFormat::Junit.render(&[test_suite()]);
+
+        let expected = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+            <testsuites tests=\"3\" failures=\"2\" time=\"1.503\">\n  \
+            <testsuite name=\"tools\" tests=\"3\" failures=\"2\" time=\"1.503\">\n    \
+            <testcase name=\"adds\" classname=\"tools\" file=\"tools/tests/unit/a.c\" line=\"4\" time=\"0.001\"/>\n    \
+            <testcase name=\"a &lt; b\" classname=\"tools\" file=\"tools/tests/unit/a.c\" line=\"9\" time=\"0.002\">\n      \
+            <failure message=\"n == 5 at tools/tests/unit/a.c:10\" type=\"assertion\">.!\nleft: 4\n</failure>\n    \
+            </testcase>\n    \
+            <testcase name=\"hangs\" classname=\"tools\" file=\"tools/tests/unit/b.c\" line=\"2\" time=\"1.500\">\n      \
+            <failure message=\"timed out after 1.5s\" type=\"timeout\"></failure>\n    \
+            </testcase>\n  \
+            </testsuite>\n\
+            </testsuites>\n";
+        assert_eq!(expected, report);
+    }
+
+    #[test]
+    fn tap_report() {
+        let report = ReportFormat::Tap.render(&[test_suite()]);
+
+        let expected = "TAP version 13\n1..3\n\
+            ok 1 - adds\n  ---\n  file: \"tools/tests/unit/a.c\"\n  line: 4\n  duration_ms: 1.000\n  ...\n\
+            not ok 2 - a < b\n  ---\n  file: \"tools/tests/unit/a.c\"\n  line: 9\n  duration_ms: 2.000\n  \
+            message: \"n == 5 at tools/tests/unit/a.c:10\"\n  severity: assertion\n  \
+            output: |-\n    .!\n    left: 4\n  ...\n\
+            not ok 3 - hangs\n  ---\n  file: \"tools/tests/unit/b.c\"\n  line: 2\n  duration_ms: 1500.000\n  \
+            message: \"timed out after 1.5s\"\n  severity: timeout\n  ...\n";
+        assert_eq!(expected, report);
+    }
+
+    #[test]
+    fn yaml_string_escapes() {
+        assert_eq!(r#""a \"b\"\n\\ \x1b""#, yaml_string("a \"b\"\n\\ \u{1b}"));
+    }
+
+    #[test]
+    fn xml_escape_invalid_chars() {
+        assert_eq!("a&amp;b\t\n\u{fffd}", xml_escape("a&b\t\n\u{1b}"));
+        assert_eq!("&lt;a&gt;&#10;", xml_attr("<a>\n"));
+    }
+
+    fn test_suite() -> Suite {
+        let result = |file: &str, line, name: &str, outcome, millis, output: &str| TestResult {
+            case: TestCase {
+                index: 0,
+                file: PathBuf::from(file),
+                line,
+                name: name.to_owned(),
+                timeout: None,
+            },
+            outcome,
+            output: output.to_owned(),
+            duration: Duration::from_millis(millis),
+            failures: Vec::new(),
+        };
+
+        let mut failed = result(
+            "tests/unit/a.c",
+            9,
+            "a < b",
+            Outcome::Failed,
+            2,
+            ".!\nleft: 4\n",
+        );
+        failed.failures.push(Failure {
+            file: PathBuf::from("tests/unit/a.c"),
+            line: 10,
+            assertion: "n == 5".to_owned(),
+        });
+        let timeout = Outcome::TimedOut(Duration::from_millis(1500));
+
+        Suite {
+            name: "tools".to_owned(),
+            dir: PathBuf::from("tools"),
+            results: vec![
+                result("tests/unit/a.c", 4, "adds", Outcome::Passed, 1, "."),
+                failed,
+                result("tests/unit/b.c", 2, "hangs", timeout, 1500, ""),
+            ],
+        }
+    }
+}
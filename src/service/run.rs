@@ -0,0 +1,195 @@
+use super::{
+    build::{bin_targets, build_workspace, BinTarget, BuildOptions},
+    workspace::{Package, Workspace},
+};
+use crate::util::error::Error;
+
+use anyhow::Context;
+
+use std::{
+    ffi::OsString,
+    path::PathBuf,
+    process::{Command, ExitStatus},
+};
+
+/// Build the workspace, then run the executable `bin` of a member with `args`
+///
+/// Without `bin`, runs the only executable, or the one named after the
+/// package. The executable inherits stdin, stdout, stderr, the environment,
+/// and the current working directory. Returns its exit status.
+#[tracing::instrument(level = "debug", skip(workspace))]
+pub fn run_project(
+    workspace: &Workspace,
+    options: &BuildOptions,
+    bin: Option<&str>,
+    args: &[OsString],
+) -> anyhow::Result<ExitStatus> {
+    let (package, target) = find_bin(workspace, bin)?;
+
+    let artifacts = build_workspace(workspace, options)?;
+    let exe = &artifacts.executables[&target.name];
+
+    tracing::info!("Running `{}`", exe.display());
+
+    Command::new(package.root.join(exe))
+        .args(args)
+        .status()
+        .with_context(|| Error::Execute(exe.clone()))
+}
+
+/// Path of the executable `bin` that [`run_project`] would run, without
+/// building it
+pub fn executable_path(
+    workspace: &Workspace,
+    options: &BuildOptions,
+    bin: Option<&str>,
+) -> anyhow::Result<PathBuf> {
+    let (package, target) = find_bin(workspace, bin)?;
+    let context = workspace.context(package, options);
+
+    Ok(package.root.join(context.executable_path(&target.name)))
+}
+
+fn find_bin<'w>(
+    workspace: &'w Workspace,
+    bin: Option<&str>,
+) -> anyhow::Result<(&'w Package, BinTarget)> {
+    let mut targets = Vec::new();
+    for package in workspace.members() {
+        for target in bin_targets(&package.root, &package.config)? {
+            targets.push((package, target));
+        }
+    }
+
+    let (package, target) = select_bin(&targets, workspace, bin)?;
+    Ok((package, target.clone()))
+}
+
+/// Target named `bin`, or by default the one named after the only member
+/// or the workspace directory
+fn select_bin<'a, 'w>(
+    targets: &'a [(&'w Package, BinTarget)],
+    workspace: &Workspace,
+    bin: Option<&str>,
+) -> anyhow::Result<&'a (&'w Package, BinTarget)> {
+    let mut members = workspace.members();
+    let default = match (members.next(), members.next()) {
+        (Some(package), None) => package.config.package.name.clone(),
+        _ => workspace
+            .root
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned(),
+    };
+    let name = bin.unwrap_or(&default);
+
+    if let Some(target) = targets.iter().find(|(_, target)| target.name == name) {
+        return Ok(target);
+    }
+
+    match (bin, targets) {
+        (None, [target]) => Ok(target),
+        (None, [_, _, ..]) => anyhow::bail!(Error::AmbiguousBin(name.to_owned())),
+        _ => anyhow::bail!(Error::NoBin(name.to_owned())),
+    }
+}
+
+/// Exit code to report for a finished process
+///
+/// On unix a process killed by a signal reports `128 + signal`, as a shell
+/// does.
+pub fn exit_code(status: &ExitStatus) -> i32 {
+    if let Some(code) = status.code() {
+        return code;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+
+    exitcode::SOFTWARE
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::util::test::testdir_project;
+
+    use std::{fs, os::unix::process::ExitStatusExt};
+
+    #[test]
+    fn exit_code_from_code() {
+        assert_eq!(3, exit_code(&ExitStatus::from_raw(3 << 8)));
+    }
+
+    #[test]
+    fn exit_code_from_signal() {
+        assert_eq!(128 + 11, exit_code(&ExitStatus::from_raw(11)));
+    }
+
+    #[test]
+    fn run_project_passes_args_and_exit_code() {
+        let (_dir, path) = testdir_project();
+        let main = "int main(int argc, char **argv) { return argc == 3 ? 7 : 1; }\n";
+        fs::write(path.join("src").join("main.c"), main).expect("write main");
+        let workspace = Workspace::load(&path).expect("load workspace");
+        let args = &["a".into(), "b".into()];
+
+        let status =
+            run_project(&workspace, &BuildOptions::default(), None, args).expect("run project");
+
+        assert_eq!(Some(7), status.code());
+    }
+
+    #[test]
+    fn run_project_selects_bin() {
+        let (_dir, path) = testdir_project();
+        let bin_dir = path.join("src").join("bin");
+        fs::create_dir(&bin_dir).expect("create bin dir");
+        fs::write(bin_dir.join("server.c"), "int main(void) { return 4; }\n").expect("write bin");
+        let workspace = Workspace::load(&path).expect("load workspace");
+        let options = BuildOptions::default();
+
+        let status = run_project(&workspace, &options, Some("server"), &[]).expect("run bin");
+        assert_eq!(Some(4), status.code());
+
+        let status = run_project(&workspace, &options, None, &[]).expect("run package bin");
+        assert_eq!(Some(0), status.code());
+    }
+
+    #[test]
+    fn run_project_ambiguous_bin() {
+        let (_dir, path) = testdir_project();
+        let bin_dir = path.join("src").join("bin");
+        fs::create_dir(&bin_dir).expect("create bin dir");
+        fs::remove_file(path.join("src").join("main.c")).expect("remove main");
+        for bin in ["client.c", "server.c"] {
+            fs::write(bin_dir.join(bin), "int main(void) { return 0; }\n").expect("write bin");
+        }
+        let workspace = Workspace::load(&path).expect("load workspace");
+
+        let err = run_project(&workspace, &BuildOptions::default(), None, &[])
+            .expect_err("ambiguous bin");
+
+        let err = err.downcast_ref::<Error>().expect("crame error");
+        assert!(matches!(err, Error::AmbiguousBin(_)));
+    }
+
+    #[test]
+    fn run_project_unknown_bin() {
+        let (_dir, path) = testdir_project();
+        let workspace = Workspace::load(&path).expect("load workspace");
+
+        let err = run_project(&workspace, &BuildOptions::default(), Some("nope"), &[])
+            .expect_err("unknown bin");
+
+        let err = err.downcast_ref::<Error>().expect("crame error");
+        assert!(matches!(err, Error::NoBin(_)));
+    }
+}
@@ -0,0 +1,499 @@
+use super::{
+    config::{Config, CONFIG_PATH},
+    init::c_identifier,
+};
+use crate::util::{error::Error, file::config_dir};
+
+use anyhow::Context;
+use git2::Repository;
+use tempfile::TempDir;
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Directory of named templates within the user config directory
+const TEMPLATES_DIR: &str = "crame/templates";
+/// Names of build system files copied without replacing placeholders, as
+/// just uses the same `{{...}}` syntax for its own interpolation
+const UNRENDERED_FILES: &[&str] = &["justfile", ".justfile"];
+
+/// Files copied into a new project instead of the built-in hello world
+#[derive(Debug)]
+pub struct Template {
+    dir: PathBuf,
+    /// Clone of a git template, removed when the template is dropped
+    _clone: Option<TempDir>,
+}
+
+/// Values of the `{{placeholder}}`s in the contents and paths of template
+/// files
+#[derive(Debug, Clone)]
+pub struct Placeholders {
+    name: String,
+    author: String,
+    year: i64,
+    std: String,
+    /// Header included by a module file
+    header: Option<String>,
+    /// Include guard of a module header
+    guard: Option<String>,
+}
+
+/// Where a template is read from
+#[derive(Debug, PartialEq, Eq)]
+enum Source {
+    Dir(PathBuf),
+    Git(String),
+}
+
+impl Template {
+    /// Template given by `spec`, cloning it first if it is a git repository
+    ///
+    /// `spec` is a URL of a git repository, such as `file:///path`, a local
+    /// directory or git repository, or the name of a template in the user
+    /// config directory.
+    #[tracing::instrument(level = "debug")]
+    pub fn load(spec: &str) -> anyhow::Result<Self> {
+        let named = config_dir().map(|dir| dir.join(TEMPLATES_DIR));
+
+        match resolve(spec, named.as_deref())? {
+            Source::Dir(dir) => Ok(Self { dir, _clone: None }),
+            Source::Git(url) => clone(&url),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Whether the template has a file or directory at `path`
+    pub fn contains(&self, path: impl AsRef<Path>) -> bool {
+        self.dir.join(path).exists()
+    }
+
+    /// Copy every file of the template into `dest`, except its git directory
+    /// and `Crame.toml`, which is used as the base of the project's config
+    ///
+    /// Placeholders are replaced in the paths of the files, and in their
+    /// contents unless they are binary or a justfile. Nothing is copied if
+    /// any of the files already exists in `dest`.
+    pub fn copy_into(&self, dest: &Path, placeholders: &Placeholders) -> anyhow::Result<()> {
+        let files: Vec<_> = template_files(&self.dir)?
+            .into_iter()
+            .map(|file| {
+                let rendered = placeholders.render(&file.to_string_lossy());
+                (file, dest.join(rendered))
+            })
+            .collect();
+
+        for (_, path) in &files {
+            if path.exists() {
+                anyhow::bail!(Error::Conflict(path.clone()));
+            }
+        }
+
+        for (file, path) in files {
+            let file = self.dir.join(file);
+            let dir = path.parent().expect("file within a directory");
+            fs::create_dir_all(dir).with_context(|| Error::CreateDir(dir.to_owned()))?;
+
+            tracing::debug!(?path, "Copying template file");
+
+            let contents = fs::read(&file).with_context(|| Error::ReadFile(file.clone()))?;
+            let contents = match String::from_utf8(contents) {
+                Ok(text) if is_rendered(&file) => placeholders.render(&text).into_bytes(),
+                Ok(text) => text.into_bytes(),
+                Err(binary) => binary.into_bytes(),
+            };
+            fs::write(&path, contents).with_context(|| Error::CreateFile(path.clone()))?;
+
+            // keeps scripts executable
+            let permissions = fs::metadata(&file)
+                .with_context(|| Error::ReadFile(file.clone()))?
+                .permissions();
+            fs::set_permissions(&path, permissions).with_context(|| Error::WriteFile(path))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Placeholders {
+    /// Values for a new project with `config`
+    ///
+    /// The author is the git `user.name`, or the login name without one.
+    pub fn new(config: &Config) -> Self {
+        let author = git2::Config::open_default()
+            .and_then(|config| config.get_string("user.name"))
+            .ok()
+            .or_else(|| env::var("USER").ok())
+            .or_else(|| env::var("USERNAME").ok())
+            .unwrap_or_default();
+
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+
+        Self {
+            name: config.package.name.clone(),
+            author,
+            year: year_of_day((secs / 86_400) as i64),
+            std: config.build.std.map_or("", |std| std.name()).to_owned(),
+            header: None,
+            guard: None,
+        }
+    }
+
+    /// Values for a module file including `header`, whose own header has the
+    /// include guard `guard`
+    pub fn with_header(&self, header: &str, guard: &str) -> Self {
+        Self {
+            header: Some(header.to_owned()),
+            guard: Some(guard.to_owned()),
+            ..self.clone()
+        }
+    }
+
+    /// Replace the `{{name}}`, `{{name_ident}}`, `{{NAME_UPPER}}`,
+    /// `{{author}}`, `{{year}}` and `{{std}}` placeholders
+    ///
+    /// `name_ident` is the package name as a valid C identifier, used for
+    /// function prefixes. `NAME_UPPER` is its uppercase form, used for include
+    /// guards. `std` is empty without a C standard in the config. Module files
+    /// also have `{{header}}` and `{{GUARD}}` replaced.
+    pub fn render(&self, template: &str) -> String {
+        if !template.contains("{{") {
+            return template.to_owned();
+        }
+
+        let ident = c_identifier(&self.name);
+
+        let mut rendered = template
+            .replace("{{name}}", &self.name)
+            .replace("{{name_ident}}", &ident)
+            .replace("{{NAME_UPPER}}", &ident.to_uppercase())
+            .replace("{{author}}", &self.author)
+            .replace("{{year}}", &self.year.to_string())
+            .replace("{{std}}", &self.std);
+
+        if let Some(header) = &self.header {
+            rendered = rendered.replace("{{header}}", header);
+        }
+        if let Some(guard) = &self.guard {
+            rendered = rendered.replace("{{GUARD}}", guard);
+        }
+
+        rendered
+    }
+}
+
+/// Whether placeholders are replaced in the contents of `file`
+fn is_rendered(file: &Path) -> bool {
+    let name = file.file_name().unwrap_or_default().to_string_lossy();
+    !UNRENDERED_FILES
+        .iter()
+        .any(|unrendered| name.eq_ignore_ascii_case(unrendered))
+}
+
+/// Gregorian year of the day `days` after 1970-01-01
+fn year_of_day(days: i64) -> i64 {
+    // civil_from_days by Howard Hinnant, with years starting in March
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+
+    // January and February belong to the next year
+    let year = era * 400 + year_of_era;
+    if month_index >= 10 {
+        year + 1
+    } else {
+        year
+    }
+}
+
+/// Source of the template `spec`, looking up names in `named`
+fn resolve(spec: &str, named: Option<&Path>) -> anyhow::Result<Source> {
+    if spec.contains("://") {
+        return Ok(Source::Git(spec.to_owned()));
+    }
+
+    let path = Path::new(spec);
+    let is_name = path.components().count() == 1 && !spec.starts_with('.');
+    let dir = match named {
+        Some(named) if is_name && !path.exists() => named.join(spec),
+        _ => path.to_owned(),
+    };
+
+    if !dir.is_dir() {
+        let location = match named {
+            Some(named) => format!("`{}`", named.display()),
+            None => "the user config directory".to_owned(),
+        };
+        anyhow::bail!(Error::NoTemplate(spec.to_owned(), location));
+    }
+
+    if Repository::open(&dir).is_ok() {
+        Ok(Source::Git(dir.to_string_lossy().into_owned()))
+    } else {
+        Ok(Source::Dir(dir))
+    }
+}
+
+/// Clone the git repository at `url` into a temporary directory
+fn clone(url: &str) -> anyhow::Result<Template> {
+    let clone = TempDir::new().context("unable to create temporary directory")?;
+
+    tracing::info!("Cloning template `{}`", url);
+
+    Repository::clone(url, clone.path()).with_context(|| Error::Clone(url.to_owned()))?;
+
+    Ok(Template {
+        dir: clone.path().to_owned(),
+        _clone: Some(clone),
+    })
+}
+
+/// Files to copy from the template at `dir`, relative to it
+fn template_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    find_template_files(dir, Path::new(""), &mut files)?;
+    files.retain(|file| file != Path::new(CONFIG_PATH));
+    files.sort();
+
+    Ok(files)
+}
+
+fn find_template_files(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    let path = root.join(dir);
+    let entries = fs::read_dir(&path).with_context(|| Error::ReadDir(path.clone()))?;
+
+    for entry in entries {
+        let entry = entry.with_context(|| Error::ReadDir(path.clone()))?;
+        if entry.file_name() == ".git" {
+            continue;
+        }
+
+        let file = dir.join(entry.file_name());
+        if entry.path().is_dir() {
+            find_template_files(root, &file, files)?;
+        } else {
+            files.push(file);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::test::testdir_and_path;
+
+    use git2::Signature;
+
+    #[test]
+    fn resolve_url_as_git() {
+        let source = resolve("file:///srv/templates/skeleton", None).expect("resolve");
+
+        assert_eq!(
+            Source::Git("file:///srv/templates/skeleton".to_owned()),
+            source
+        );
+    }
+
+    #[test]
+    fn resolve_named_template() {
+        let (_dir, named) = testdir_and_path();
+        fs::create_dir(named.join("skeleton")).expect("create template");
+
+        let source = resolve("skeleton", Some(&named)).expect("resolve");
+
+        assert_eq!(Source::Dir(named.join("skeleton")), source);
+    }
+
+    #[test]
+    fn resolve_missing_template_error() {
+        let (_dir, named) = testdir_and_path();
+
+        let err = resolve("skeleton", Some(&named)).expect_err("no template");
+
+        let err = err.downcast_ref::<Error>().expect("crame error");
+        assert!(matches!(err, Error::NoTemplate(..)));
+        assert!(err.to_string().contains(&named.display().to_string()));
+    }
+
+    #[test]
+    fn copy_dir_template() {
+        let (_template_dir, template) = skeleton_template();
+        let (_dir, dest) = testdir_and_path();
+
+        let template = Template::load(template.to_str().unwrap()).expect("load template");
+        template
+            .copy_into(&dest, &placeholders())
+            .expect("copy template");
+
+        assert_skeleton(&dest);
+        assert!(!dest.join(CONFIG_PATH).exists());
+    }
+
+    #[test]
+    fn copy_git_template() {
+        let (_template_dir, template) = skeleton_template();
+        let repo = Repository::init(&template).expect("init repo");
+        fs::write(template.join("untracked.c"), "").expect("write untracked file");
+        commit_all(&repo, &["src/main.c", "src/log/log.h"]);
+        let (_dir, dest) = testdir_and_path();
+
+        let template = Template::load(template.to_str().unwrap()).expect("load template");
+        template
+            .copy_into(&dest, &placeholders())
+            .expect("copy template");
+
+        assert_skeleton(&dest);
+        assert!(!dest.join(".git").exists());
+        assert!(!dest.join("untracked.c").exists());
+    }
+
+    #[test]
+    fn copy_template_conflict() {
+        let (_template_dir, template) = skeleton_template();
+        let (_dir, dest) = testdir_and_path();
+        fs::create_dir(dest.join("src")).expect("create dir");
+        fs::write(dest.join("src").join("main.c"), "").expect("write main");
+
+        let template = Template::load(template.to_str().unwrap()).expect("load template");
+        let err = template
+            .copy_into(&dest, &placeholders())
+            .expect_err("existing file");
+
+        let err = err.downcast_ref::<Error>().expect("crame error");
+        assert!(matches!(err, Error::Conflict(_)));
+        assert!(!dest.join("src").join("log").exists());
+    }
+
+    #[test]
+    fn copy_renders_placeholders() {
+        let (_template_dir, template) = testdir_and_path();
+        fs::create_dir(template.join("include")).expect("create dir");
+        fs::write(
+            template.join("include").join("{{name}}.h"),
+            "#ifndef {{NAME_UPPER}}_H\n// {{author}} {{year}} {{std}} {{other}}\n",
+        )
+        .expect("write header");
+        fs::write(template.join("logo.bin"), b"{{name}}\xff").expect("write binary");
+        let (_dir, dest) = testdir_and_path();
+
+        let template = Template::load(template.to_str().unwrap()).expect("load template");
+        template
+            .copy_into(&dest, &placeholders())
+            .expect("copy template");
+
+        assert_eq!(
+            "#ifndef MY_APP_H\n// Ada 2024 c11 {{other}}\n",
+            fs::read_to_string(dest.join("include").join("my-app.h")).expect("read header")
+        );
+        assert_eq!(
+            b"{{name}}\xff".to_vec(),
+            fs::read(dest.join("logo.bin")).expect("read binary")
+        );
+    }
+
+    #[test]
+    fn copy_keeps_justfile_interpolation() {
+        let (_template_dir, template) = testdir_and_path();
+        let justfile = "run *args:\n    crame run -- {{args}}\n\nname := \"{{name}}\"\n";
+        fs::write(template.join("justfile"), justfile).expect("write justfile");
+        let (_dir, dest) = testdir_and_path();
+
+        let template = Template::load(template.to_str().unwrap()).expect("load template");
+        template
+            .copy_into(&dest, &placeholders())
+            .expect("copy template");
+
+        assert_eq!(
+            justfile,
+            fs::read_to_string(dest.join("justfile")).expect("read justfile")
+        );
+    }
+
+    #[test]
+    fn render_name_placeholders() {
+        let template = "{{name}} {{name_ident}} {{NAME_UPPER}}";
+        let mut placeholders = placeholders();
+
+        assert_eq!("my-app my_app MY_APP", placeholders.render(template));
+        placeholders.name = "2d".to_owned();
+        assert_eq!("2d _2d _2D", placeholders.render(template));
+    }
+
+    #[test]
+    fn render_module_placeholders() {
+        let template = "#include \"{{header}}\"\n#ifndef {{GUARD}}\n";
+
+        assert_eq!(template, placeholders().render(template));
+        assert_eq!(
+            "#include \"str.h\"\n#ifndef MY_APP_STR_H\n",
+            placeholders()
+                .with_header("str.h", "MY_APP_STR_H")
+                .render(template)
+        );
+    }
+
+    #[test]
+    fn year_of_days_since_epoch() {
+        assert_eq!(1970, year_of_day(0));
+        assert_eq!(1999, year_of_day(10_956));
+        assert_eq!(2000, year_of_day(10_957));
+        assert_eq!(2000, year_of_day(11_016));
+        assert_eq!(2026, year_of_day(20_744));
+        assert_eq!(1969, year_of_day(-1));
+    }
+
+    fn placeholders() -> Placeholders {
+        Placeholders {
+            name: "my-app".to_owned(),
+            author: "Ada".to_owned(),
+            year: 2024,
+            std: "c11".to_owned(),
+            header: None,
+            guard: None,
+        }
+    }
+
+    fn skeleton_template() -> (TempDir, PathBuf) {
+        let (dir, path) = testdir_and_path();
+        fs::create_dir_all(path.join("src").join("log")).expect("create dirs");
+        fs::write(path.join("src").join("main.c"), "int main(void) {}\n").expect("write main");
+        fs::write(path.join("src").join("log").join("log.h"), "").expect("write header");
+        fs::write(path.join(CONFIG_PATH), "").expect("write config");
+        (dir, path)
+    }
+
+    fn assert_skeleton(dest: &Path) {
+        assert_eq!(
+            "int main(void) {}\n",
+            fs::read_to_string(dest.join("src").join("main.c")).expect("read main")
+        );
+        assert!(dest.join("src").join("log").join("log.h").exists());
+    }
+
+    fn commit_all(repo: &Repository, files: &[&str]) {
+        let mut index = repo.index().expect("open index");
+        for file in files {
+            index.add_path(Path::new(file)).expect("add file");
+        }
+        let tree = repo
+            .find_tree(index.write_tree().expect("write tree"))
+            .expect("find tree");
+        let signature = Signature::now("crame", "crame@example.com").expect("signature");
+        repo.commit(Some("HEAD"), &signature, &signature, "skeleton", &tree, &[])
+            .expect("commit");
+    }
+}
@@ -0,0 +1,580 @@
+use super::{
+    build::{
+        bin_targets, build_package, check_bin_names, emit_artifact, shared_sources, ArtifactKind,
+        BuildContext, BuildOptions,
+    },
+    compdb,
+    compiler::Compiler,
+    config::{CStandard, Config},
+    harness::{self, TestFilter},
+    report::{Report, Suite},
+    workspace::Workspace,
+};
+use crate::util::{
+    error::Error,
+    file::{find_files, relative_path},
+    message,
+};
+
+use anyhow::Context;
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+pub const UNIT_DIR: &str = "tests/unit";
+pub const RUNNER_FILE: &str = "tests/run.c";
+const AGGREGATE_FILE: &str = "test_all.c";
+pub const LEGACY_AGGREGATE_FILE: &str = "tests/test_all.c";
+const TEST_EXE: &str = "test";
+
+/// Build and run the tests of each workspace member selected by `filter`
+///
+/// Every member's tests are run, even after one fails. With a `report`, the
+/// results are also written in its format. The usual output goes to stderr
+/// if the report or JSON messages go to stdout.
+#[tracing::instrument(level = "debug", skip_all, fields(root = ?workspace.root))]
+pub fn test_project(
+    workspace: &Workspace,
+    options: &BuildOptions,
+    filter: &TestFilter,
+    report: Option<&Report>,
+) -> anyhow::Result<()> {
+    let report_to_stdout = matches!(report, Some(Report { output: None, .. }));
+    let mut out: Box<dyn Write> = if report_to_stdout || message::is_json() {
+        Box::new(io::stderr())
+    } else {
+        Box::new(io::stdout())
+    };
+    let mut suites = Vec::new();
+    let mut failed = Vec::new();
+
+    for (context, package) in build_workspace_tests(workspace, options)? {
+        tracing::info!("Running `{}`", package.exe.display());
+
+        let timeout = package.config.test.timeout();
+        let name = &package.config.package.name;
+        let run = harness::run_tests(&context.root, &package.exe, name, timeout, filter, &mut out)?;
+        if !run.passed {
+            failed.push(package.config.package.name.as_str());
+        }
+
+        suites.push(Suite {
+            name: package.config.package.name.clone(),
+            dir: relative_path(&workspace.root, &context.root),
+            results: run.results,
+        });
+    }
+
+    if let Some(report) = report {
+        write_report(report, &suites)?;
+    }
+
+    if !failed.is_empty() {
+        anyhow::bail!(Error::TestFailed(failed.join("`, `")));
+    }
+
+    Ok(())
+}
+
+fn write_report(report: &Report, suites: &[Suite]) -> anyhow::Result<()> {
+    let contents = report.format.render(suites);
+
+    match &report.output {
+        Some(path) => {
+            tracing::info!("Writing test report to `{}`", path.display());
+            fs::write(path, contents).with_context(|| Error::WriteFile(path.to_owned()))?;
+        }
+        None => io::stdout().write_all(contents.as_bytes())?,
+    }
+
+    Ok(())
+}
+
+/// Build the tests of each workspace member and print those selected by
+/// `filter`, without running them
+pub fn list_tests(
+    workspace: &Workspace,
+    options: &BuildOptions,
+    filter: &TestFilter,
+) -> anyhow::Result<()> {
+    for (context, package) in build_workspace_tests(workspace, options)? {
+        let name = &package.config.package.name;
+        harness::print_tests(&context.root, &package.exe, name, filter)?;
+    }
+
+    Ok(())
+}
+
+/// Test executable of a workspace member
+struct TestExe<'a> {
+    config: &'a Config,
+    exe: PathBuf,
+}
+
+/// Build the tests of every member, and the dependencies they link
+fn build_workspace_tests<'a>(
+    workspace: &'a Workspace,
+    options: &BuildOptions,
+) -> anyhow::Result<Vec<(BuildContext, TestExe<'a>)>> {
+    check_bin_names(workspace)?;
+    let mut tests = Vec::new();
+
+    // packages are in dependency order, so each member's libraries are built
+    // before the tests linking them
+    for package in &workspace.packages {
+        let context = workspace.context(package, options);
+        build_package(&context, &package.config)?;
+
+        if !package.member {
+            continue;
+        }
+
+        let exe = build_tests(&context, &package.config)?;
+        tests.push((
+            context,
+            TestExe {
+                config: &package.config,
+                exe,
+            },
+        ));
+    }
+
+    Ok(tests)
+}
+
+/// Build the test executable into the profile's output directory
+///
+/// Test files in `tests/unit` are discovered and included into a generated
+/// `test_all.c` in the object directory, which is compiled with `tests/run.c`
+/// and the sources shared by every target. Returns the path to the executable,
+/// relative to the package root.
+pub fn build_tests(context: &BuildContext, config: &Config) -> anyhow::Result<PathBuf> {
+    let path = context.root.as_path();
+    tracing::info!(
+        "Compiling tests for `{}` [{}]",
+        config.package.name,
+        context.profile.dir_name()
+    );
+
+    let tests = discover_tests(path)?;
+    tracing::debug!(?tests, "Discovered tests");
+
+    context.create_out_dir()?;
+    compdb::update(context, config)?;
+    write_aggregate(path, &context.obj_dir(), &tests)?;
+
+    if path.join(LEGACY_AGGREGATE_FILE).exists() {
+        tracing::warn!(
+            "`{}` is used instead of discovered tests, remove it to include all tests in `{}`",
+            LEGACY_AGGREGATE_FILE,
+            UNIT_DIR,
+        );
+    }
+
+    let sources = shared_sources(path, &bin_targets(path, config)?)?;
+
+    let name = format!("{} tests", config.package.name);
+    let output = match &context.package {
+        Some(package) => context.executable_path(&format!("{}-{}", TEST_EXE, package)),
+        None => context.executable_path(TEST_EXE),
+    };
+    let runner_compiler = runner_compiler(context, config);
+    let runner = &[PathBuf::from(RUNNER_FILE)];
+
+    let mut objects = context.compile_objects(&context.compiler, &sources, &name)?;
+    objects.extend(context.compile_objects(&runner_compiler, runner, &name)?);
+    objects.paths.extend(context.libraries.iter().cloned());
+
+    let invocation = context.compiler.link(&objects.paths, &output);
+    context.link(&invocation, &objects, &output, &name)?;
+    emit_artifact(
+        context,
+        &config.package.name,
+        TEST_EXE,
+        ArtifactKind::Test,
+        &output,
+    );
+
+    Ok(output)
+}
+
+/// Compiler for `tests/run.c`
+///
+/// Only the runner needs to find the generated aggregate in the object
+/// directory. The runner uses variadic macros and compound literals, so it
+/// and the tests it includes are compiled as C99 in C89 projects.
+pub fn runner_compiler(context: &BuildContext, config: &Config) -> Compiler {
+    let compiler = context.compiler.clone().include_dir(context.obj_dir());
+
+    match config.build.std {
+        Some(CStandard::C89) => compiler.std(CStandard::C99),
+        _ => compiler,
+    }
+}
+
+/// All `.c` files in `tests/unit`, relative to the project root
+pub fn discover_tests(root: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let tests = find_files(&root.join(UNIT_DIR), "c")?
+        .into_iter()
+        .map(|file| {
+            let relative = file.strip_prefix(root).expect("found file within root");
+            relative.to_owned()
+        })
+        .collect();
+
+    Ok(tests)
+}
+
+fn write_aggregate(root: &Path, obj_dir: &Path, tests: &[PathBuf]) -> anyhow::Result<()> {
+    let path = root.join(obj_dir).join(AGGREGATE_FILE);
+    let contents = aggregate_contents(&relative_path(&root.join(obj_dir), root), tests);
+
+    // rewriting unchanged contents would cause the runner to be recompiled
+    if fs::read_to_string(&path).ok().as_deref() != Some(contents.as_str()) {
+        tracing::debug!(?path, "Writing test aggregate");
+        fs::write(&path, contents).with_context(|| Error::WriteFile(path.to_owned()))?;
+    }
+
+    Ok(())
+}
+
+/// Contents of `test_all.c`, including each test through `to_root`, the
+/// path from the aggregate's directory to the package root
+///
+/// Each test is given an id in `CRAME_FILE`, and followed by `CRAME_FILE_END`,
+/// which runners use for the hooks of each file.
+fn aggregate_contents(to_root: &Path, tests: &[PathBuf]) -> String {
+    let mut contents = String::from(
+        "// generated by crame, do not edit\n\
+        #ifndef CRAME_FILE_END\n\
+        #define CRAME_FILE_END\n\
+        #endif\n",
+    );
+
+    for (id, test) in tests.iter().enumerate() {
+        contents += &format!("#define CRAME_FILE {}\n", id);
+        contents += "#include \"";
+        contents += &include_path(&to_root.join(test));
+        contents += "\"\nCRAME_FILE_END\n#undef CRAME_FILE\n";
+    }
+
+    contents
+}
+
+/// Path with `/` separators, as used in `#include` directives
+pub fn include_path(path: &Path) -> String {
+    path.iter()
+        .map(|part| part.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        service::{config::Warnings, profile::Profile},
+        util::test::{build_unit_tests, testdir_and_path, testdir_project, testdir_workspace},
+    };
+
+    use std::{process::Command, time::Duration};
+
+    #[test]
+    fn discover_tests_nested() {
+        let (_dir, path) = testdir_and_path();
+        fs::create_dir_all(path.join(UNIT_DIR).join("str")).expect("create dirs");
+        for file in ["tests/run.c", "tests/unit/a.c", "tests/unit/str/b.c"] {
+            fs::write(path.join(file), "").expect("write file");
+        }
+
+        let tests = discover_tests(&path).expect("discover tests");
+
+        let expected = vec![
+            PathBuf::from(UNIT_DIR).join("a.c"),
+            PathBuf::from(UNIT_DIR).join("str").join("b.c"),
+        ];
+        assert_eq!(expected, tests);
+    }
+
+    #[test]
+    fn aggregate_includes_tests() {
+        let tests = &[
+            PathBuf::from(UNIT_DIR).join("a.c"),
+            PathBuf::from(UNIT_DIR).join("str").join("b.c"),
+        ];
+
+        let contents = aggregate_contents(&PathBuf::from("../.."), tests);
+
+        let expected = "// generated by crame, do not edit\n\
+            #ifndef CRAME_FILE_END\n\
+            #define CRAME_FILE_END\n\
+            #endif\n\
+            #define CRAME_FILE 0\n\
+            #include \"../../tests/unit/a.c\"\n\
+            CRAME_FILE_END\n\
+            #undef CRAME_FILE\n\
+            #define CRAME_FILE 1\n\
+            #include \"../../tests/unit/str/b.c\"\n\
+            CRAME_FILE_END\n\
+            #undef CRAME_FILE\n";
+        assert_eq!(expected, contents);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_project_success() {
+        let (_dir, path) = testdir_project();
+        let workspace = Workspace::load(&path).expect("load workspace");
+
+        test_project(
+            &workspace,
+            &BuildOptions::default(),
+            &TestFilter::default(),
+            None,
+        )
+        .expect("tests pass");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_project_discovers_new_test() {
+        let (_dir, path) = testdir_project();
+        let workspace = Workspace::load(&path).expect("load workspace");
+        let failing = "#if defined TESTS\nTEST(\"fails\") { ASSERT(0); }\n#endif\n";
+        fs::write(path.join(UNIT_DIR).join("fails.c"), failing).expect("write test");
+
+        let err = test_project(
+            &workspace,
+            &BuildOptions::default(),
+            &TestFilter::default(),
+            None,
+        )
+        .expect_err("tests fail");
+
+        let err = err.downcast_ref::<Error>().expect("crame error");
+        assert!(matches!(err, Error::TestFailed(_)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_project_links_member_library() {
+        let (_dir, path) = testdir_workspace();
+        let tests = "#if defined HEADERS\n#include \"mylib/mylib.h\"\n#elif defined TESTS\n\
+            TEST(\"adds\") { ASSERT(mylib_add(2, 3) == 5); }\n\
+            #endif\n";
+        fs::write(path.join("tools").join(UNIT_DIR).join("add.c"), tests).expect("write test");
+        let workspace = Workspace::load(&path).expect("load workspace");
+
+        test_project(
+            &workspace,
+            &BuildOptions::default(),
+            &TestFilter::default(),
+            None,
+        )
+        .expect("tests pass");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runner_isolates_crashing_test() {
+        let tests = "#if defined HEADERS\n#include <stdlib.h>\n#elif defined TESTS\n\
+            TEST(\"aborts\") { abort(); }\n\
+            #endif\n";
+        let (_dir, path, config, exe) = build_unit_tests("crash.c", tests);
+
+        let case = harness::test_cases(&path, &exe)
+            .expect("list tests")
+            .remove(0);
+        let timeout = config.test.timeout();
+        let result = harness::run_test(&path, &exe, case, timeout).expect("run test");
+        assert_eq!(harness::Outcome::Signaled(6), result.outcome);
+
+        let filter = TestFilter::default();
+        let run = harness::run_tests(&path, &exe, "test", timeout, &filter, &mut io::sink())
+            .expect("run tests");
+        assert!(!run.passed);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runner_stops_test_after_timeout() {
+        let tests = "#if defined TESTS\n\
+            TEST(\"hangs\", .timeout = 0.1) { for (;;) {} }\n\
+            #endif\n";
+        let (_dir, path, config, exe) = build_unit_tests("hang.c", tests);
+
+        let case = harness::test_cases(&path, &exe)
+            .expect("list tests")
+            .remove(0);
+        let result = harness::run_test(&path, &exe, case, config.test.timeout()).expect("run test");
+
+        let timeout = Duration::from_millis(100);
+        assert_eq!(harness::Outcome::TimedOut(timeout), result.outcome);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runner_stops_test_whose_child_keeps_output_open() {
+        let tests = "#if defined HEADERS\n#include <unistd.h>\n#elif defined TESTS\n\
+            TEST(\"forks\", .timeout = 0.1) { if (fork() == 0) { sleep(3); _exit(0); } for (;;) {} }\n\
+            #endif\n";
+        let (_dir, path, config, exe) = build_unit_tests("fork.c", tests);
+
+        let case = harness::test_cases(&path, &exe)
+            .expect("list tests")
+            .remove(0);
+        let start = std::time::Instant::now();
+        let result = harness::run_test(&path, &exe, case, config.test.timeout()).expect("run test");
+
+        assert!(matches!(result.outcome, harness::Outcome::TimedOut(_)));
+        assert!(
+            start.elapsed() < Duration::from_secs(2),
+            "{:?}",
+            start.elapsed()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runner_builds_with_strict_warnings() {
+        let (_dir, mut path) = testdir_project();
+        let mut config = Config::load_from_dir(&mut path).expect("load config");
+        config.build.warnings = Some(Warnings::Strict);
+        config.build.deny_warnings = true;
+        let tests = "#if defined TESTS\n\
+            TEST(\"slow\", .timeout = 0.5) { ASSERT_EQ(1, 1); }\n\
+            #endif\n";
+        fs::write(path.join(UNIT_DIR).join("slow.c"), tests).expect("write test");
+
+        for profile in [Profile::Debug, Profile::Release] {
+            let options = BuildOptions {
+                profile,
+                ..BuildOptions::default()
+            };
+            let context = BuildContext::new(&path, &config, &options);
+
+            let exe = build_tests(&context, &config).expect("build tests");
+            let status = Command::new(path.join(exe)).status().expect("run tests");
+            assert!(status.success());
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runner_builds_in_c89_project() {
+        let (_dir, mut path) = testdir_project();
+        let mut config = Config::load_from_dir(&mut path).expect("load config");
+        config.build.std = Some(CStandard::C89);
+        config.build.warnings = Some(Warnings::Pedantic);
+        config.build.deny_warnings = true;
+        let context = BuildContext::new(&path, &config, &BuildOptions::default());
+
+        let exe = build_tests(&context, &config).expect("build tests");
+
+        let status = Command::new(path.join(exe)).status().expect("run tests");
+        assert!(status.success());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runner_continues_after_failure() {
+        let tests = "#if defined TESTS\n\
+            TEST(\"fails\") { ASSERT(1 == 2); ASSERT(0); }\n\
+            TEST(\"passes\") { ASSERT(1); }\n\
+            #endif\n";
+        let (_dir, path, _, exe) = build_unit_tests("more.c", tests);
+        let output = Command::new(path.join(exe)).output().expect("run tests");
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert_eq!(Some(1), output.status.code());
+        assert!(stdout.contains("fails: 1 == 2"), "{}", stdout);
+        assert!(!stdout.contains("fails: 0"), "{}", stdout);
+        assert!(stdout.ends_with("2 passed, 1 failed\n"), "{}", stdout);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runner_prints_compared_values() {
+        let tests = "#if defined TESTS\n\
+            TEST(\"eq\") { int n = 3; ASSERT_EQ(n + 1, 5u); }\n\
+            TEST(\"str\") { ASSERT_STR_EQ(\"foo\", \"bar\"); }\n\
+            TEST(\"lt\") { ASSERT_LT(-1, 1u); ASSERT_NEAR(0.5, 0.6, 0.2); }\n\
+            #endif\n";
+        let (_dir, path, _, exe) = build_unit_tests("values.c", tests);
+        let output = Command::new(path.join(exe)).output().expect("run tests");
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(
+            stdout.contains("eq: n + 1 == 5u\n      left: 4\n     right: 5\n"),
+            "{}",
+            stdout
+        );
+        assert!(
+            stdout.contains("left: \"foo\"\n     right: \"bar\"\n"),
+            "{}",
+            stdout
+        );
+        assert!(stdout.ends_with("2 passed, 2 failed\n"), "{}", stdout);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runner_runs_hooks_around_tests() {
+        let tests = "#if defined HEADERS\n\
+            BEFORE_ALL { printf(\"<all \"); }\n\
+            AFTER_ALL { printf(\"all>\"); }\n\
+            BEFORE_EACH { printf(\"<each \"); }\n\
+            AFTER_EACH { printf(\" each>\"); }\n\
+            #elif defined TESTS\n\
+            TEST(\"fails\") { ASSERT(0); }\n\
+            TEST(\"passes\") { ASSERT(1); }\n\
+            #endif\n";
+        let (_dir, path, _, exe) = build_unit_tests("hooks.c", tests);
+        let output = Command::new(path.join(exe)).output().expect("run tests");
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let expected = "<all <each !\nTest failed at ";
+        assert!(stdout.contains(expected), "{}", stdout);
+        assert!(
+            stdout.contains("fails: 0\n each><each . each>all>"),
+            "{}",
+            stdout
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runner_reports_failed_assertions() {
+        let tests = "#if defined TESTS\n\
+            TEST(\"fails\") {\n\
+            ASSERT_EQ(1, 2);\n\
+            }\n\
+            #endif\n";
+        let (_dir, path, config, exe) = build_unit_tests("report.c", tests);
+
+        let filter = TestFilter {
+            pattern: Some("fails".to_owned()),
+            ..TestFilter::default()
+        };
+        let run = harness::run_tests(
+            &path,
+            &exe,
+            "test",
+            config.test.timeout(),
+            &filter,
+            &mut io::sink(),
+        )
+        .expect("run tests");
+
+        let expected = vec![harness::Failure {
+            file: PathBuf::from(UNIT_DIR).join("report.c"),
+            line: 3,
+            assertion: "1 == 2".to_owned(),
+        }];
+        assert_eq!(1, run.results.len());
+        assert_eq!(expected, run.results[0].failures);
+    }
+}
@@ -8,31 +8,32 @@ use std::{
 
 use crate::util::error;
 
-#[derive(Debug, Clone, Copy, ValueEnum)]
+#[derive(Debug, Default, Clone, Copy, ValueEnum)]
 pub enum VersionControl {
+    #[default]
     Git,
     None,
 }
 
-impl Default for VersionControl {
-    fn default() -> Self {
-        Self::Git
-    }
-}
-
-const GIT_IGNORES: &[&str] = &["target/"];
+const GIT_IGNORES: &[&str] = &["target/", "compile_commands.json"];
 
 pub fn init_vcs(vcs: VersionControl, path: &mut PathBuf) -> anyhow::Result<()> {
     match vcs {
         VersionControl::None => (),
         VersionControl::Git => {
-            let repo = existing_git_repo(path);
-
-            if let Some(ref repo) = repo {
-                tracing::debug!(path = ?repo.path(), "Existing repo found");
-            } else {
-                git_init(path);
-            }
+            let repo = match existing_git_repo(path) {
+                Some(repo) => {
+                    tracing::debug!(path = ?repo.path(), "Existing repo found");
+                    Some(repo)
+                }
+                // rules of an existing `.gitignore`, such as a template's,
+                // are only known once the repo exists
+                None if path.join(".gitignore").exists() => git_init(path),
+                None => {
+                    git_init(path);
+                    None
+                }
+            };
 
             let ignores = git_ignores(&repo)?;
 
@@ -47,10 +48,7 @@ pub fn init_vcs(vcs: VersionControl, path: &mut PathBuf) -> anyhow::Result<()> {
 fn existing_git_repo(path: &Path) -> Option<Repository> {
     tracing::debug!("Checking for existing git repo`");
 
-    match Repository::discover(path) {
-        Ok(repo) => Some(repo),
-        Err(_) => None,
-    }
+    Repository::discover(path).ok()
 }
 
 fn git_ignores(repo: &Option<Repository>) -> anyhow::Result<String> {
@@ -81,7 +79,15 @@ fn write_git_ignore(path: &mut PathBuf, contents: &str) {
     path.push(".gitignore");
 
     tracing::debug!(?path, "Writing ignore rules");
-    if let Err(err) = fs::write(&path, contents) {
+
+    // rules are added after those of an existing file
+    let mut rules = fs::read_to_string(&path).unwrap_or_default();
+    if !rules.is_empty() && !rules.ends_with('\n') {
+        rules.push('\n');
+    }
+    rules += contents;
+
+    if let Err(err) = fs::write(&path, rules) {
         error::report(&err.into(), true);
     }
 
@@ -89,12 +95,12 @@ fn write_git_ignore(path: &mut PathBuf, contents: &str) {
     path.pop();
 }
 
-fn git_init(path: &Path) {
+fn git_init(path: &Path) -> Option<Repository> {
     tracing::debug!("Initializing git repo");
 
-    if let Err(err) = Repository::init(path) {
-        error::report(&err.into(), true);
-    }
+    Repository::init(path)
+        .map_err(|err| error::report(&err.into(), true))
+        .ok()
 }
 
 #[cfg(test)]
@@ -156,6 +162,17 @@ mod tests {
         assert!(!git_dir_path.exists());
     }
 
+    #[test]
+    fn git_dir_with_ignore_keeps_rules() {
+        let (_dir, path) = testdir_and_path();
+        fs::write(path.join(".gitignore"), "target/\n*.o").expect("write ignore");
+
+        call_git_init_vcs(&path);
+
+        let contents = fs::read_to_string(path.join(".gitignore")).expect("read ignore");
+        assert_eq!("target/\n*.o\ncompile_commands.json\n", contents);
+    }
+
     fn git_empty_dir_init_vcs() -> TempDir {
         let (dir, path) = testdir_and_path();
         call_git_init_vcs(&path);
@@ -191,7 +208,7 @@ mod tests {
 
     fn assert_full_ignore(dir: &Path) {
         let ignore_path = dir.join(".gitignore");
-        let expected_contents = "target/\n";
+        let expected_contents = "target/\ncompile_commands.json\n";
         let contents = fs::read_to_string(ignore_path).expect(".gitignore created");
         assert_eq!(expected_contents, contents);
     }
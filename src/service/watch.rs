@@ -0,0 +1,378 @@
+use super::{
+    build::{BuildOptions, TARGET_DIR},
+    config::CONFIG_PATH,
+    profile::Profile,
+    run::executable_path,
+    workspace::Workspace,
+};
+use crate::util::{error::Error, message};
+
+use anyhow::Context;
+use clap::ValueEnum;
+use git2::Repository;
+use notify::{
+    event::{MetadataKind, ModifyKind},
+    Event, EventKind, RecursiveMode, Watcher,
+};
+
+use std::{
+    env,
+    ffi::OsString,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    time::Duration,
+};
+
+/// Directories of each package whose changes trigger a rerun
+const WATCHED_DIRS: &[&str] = &["src", "lib", "include", "tests"];
+
+/// Time without further changes before rerunning, so saving several files
+/// at once only reruns once
+const DEBOUNCE: Duration = Duration::from_millis(200);
+/// How often a running executable is checked for having exited
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Command rerun by `crame watch`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum WatchAction {
+    Build,
+    #[default]
+    Test,
+    Run,
+}
+
+/// Run `action` on the workspace, then again whenever one of its sources,
+/// tests or manifests changes, until interrupted
+///
+/// Each build is run by a new crame process, and the screen only cleared
+/// once it is done, so the previous result stays visible meanwhile. `run`
+/// starts the executable `bin` with `args` after each successful build,
+/// stopping it first if it is still running.
+pub fn watch(
+    workspace: &Workspace,
+    options: &BuildOptions,
+    action: WatchAction,
+    bin: Option<&str>,
+    args: &[OsString],
+) -> anyhow::Result<()> {
+    let (sender, events) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = sender.send(event);
+    })
+    .context("unable to start watching files")?;
+
+    let filter = ChangeFilter::new(workspace);
+    for (path, mode) in filter.watch_paths() {
+        watcher
+            .watch(&path, mode)
+            .with_context(|| Error::Watch(path.clone()))?;
+    }
+
+    loop {
+        let succeeded = rerun(&crame_args(action, options, message::is_json()))?;
+
+        let mut program = match action {
+            WatchAction::Run if succeeded => Some(start_executable(workspace, options, bin, args)?),
+            _ => None,
+        };
+
+        tracing::info!("Watching for changes, press Ctrl-C to stop");
+        wait_for_change(&events, &filter, program.as_mut())?;
+
+        if let Some(mut program) = program {
+            if program.try_wait()?.is_none() {
+                tracing::info!("Stopping executable");
+                let _ = program.kill();
+                program.wait()?;
+            }
+        }
+    }
+}
+
+/// Arguments of the crame process doing the build or tests of `action`,
+/// printing JSON messages if `json`
+fn crame_args(action: WatchAction, options: &BuildOptions, json: bool) -> Vec<OsString> {
+    let command = match action {
+        WatchAction::Test => "test",
+        WatchAction::Build | WatchAction::Run => "build",
+    };
+    let mut args = vec![OsString::from(command)];
+
+    if options.profile == Profile::Release {
+        args.push("--release".into());
+    }
+    if let Some(jobs) = options.jobs {
+        args.push("--jobs".into());
+        args.push(jobs.to_string().into());
+    }
+    if json {
+        args.extend(["--message-format".into(), "json".into()]);
+    }
+
+    args
+}
+
+/// Run crame with `args`, clearing the screen before printing its output
+///
+/// Returns whether it succeeded.
+fn rerun(args: &[OsString]) -> anyhow::Result<bool> {
+    let exe = env::current_exe().context("unable to find the crame executable")?;
+
+    // one pipe for stdout and stderr keeps their output in order
+    let (mut reader, writer) = io::pipe()?;
+    let mut child = Command::new(&exe)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(writer.try_clone()?)
+        .stderr(writer)
+        .spawn()
+        .with_context(|| Error::Execute(exe.clone()))?;
+
+    let mut output = Vec::new();
+    reader.read_to_end(&mut output)?;
+    let status = child.wait()?;
+
+    let mut stdout = io::stdout().lock();
+    if !message::is_json() {
+        // clear the screen and move the cursor to the top left
+        stdout.write_all(b"\x1b[2J\x1b[H")?;
+    }
+    stdout.write_all(&output)?;
+    stdout.flush()?;
+
+    Ok(status.success())
+}
+
+fn start_executable(
+    workspace: &Workspace,
+    options: &BuildOptions,
+    bin: Option<&str>,
+    args: &[OsString],
+) -> anyhow::Result<Child> {
+    let exe = executable_path(workspace, options, bin)?;
+    tracing::info!("Running `{}`", exe.display());
+
+    Command::new(&exe)
+        .args(args)
+        .spawn()
+        .with_context(|| Error::Execute(exe.clone()))
+}
+
+/// Block until a relevant file changes and no more changes follow
+///
+/// A running `program` is waited on meanwhile, so its exit is not left
+/// unreaped.
+fn wait_for_change(
+    events: &Receiver<notify::Result<Event>>,
+    filter: &ChangeFilter,
+    mut program: Option<&mut Child>,
+) -> anyhow::Result<()> {
+    loop {
+        let event = match events.recv_timeout(POLL_INTERVAL) {
+            Ok(event) => event,
+            Err(RecvTimeoutError::Timeout) => {
+                let exited = match program.as_deref_mut() {
+                    Some(program) => program.try_wait()?,
+                    None => None,
+                };
+                if let Some(status) = exited {
+                    tracing::info!("Executable exited with {}", status);
+                    program = None;
+                }
+                continue;
+            }
+            Err(RecvTimeoutError::Disconnected) => anyhow::bail!("file watcher stopped"),
+        };
+
+        match event {
+            Ok(event) if filter.is_relevant(&event) => {
+                tracing::debug!(paths = ?event.paths, "Change detected");
+                break;
+            }
+            Ok(_) => {}
+            Err(err) => tracing::warn!("Error watching files: {}", err),
+        }
+    }
+
+    loop {
+        match events.recv_timeout(DEBOUNCE) {
+            Ok(_) => {}
+            Err(RecvTimeoutError::Timeout) => return Ok(()),
+            Err(RecvTimeoutError::Disconnected) => anyhow::bail!("file watcher stopped"),
+        }
+    }
+}
+
+/// Decides which changed files trigger a rerun
+struct ChangeFilter {
+    /// Watched directories of every package
+    dirs: Vec<PathBuf>,
+    /// `Crame.toml` of every package, and the workspace
+    manifests: Vec<PathBuf>,
+    target_dir: PathBuf,
+    /// Repository whose ignore rules apply to the workspace
+    repo: Option<Repository>,
+}
+
+impl ChangeFilter {
+    fn new(workspace: &Workspace) -> Self {
+        let roots = workspace
+            .packages
+            .iter()
+            .map(|package| package.root.as_path())
+            .chain([workspace.root.as_path()]);
+
+        let mut dirs = Vec::new();
+        let mut manifests = Vec::new();
+        for root in roots {
+            dirs.extend(WATCHED_DIRS.iter().map(|dir| root.join(dir)));
+            manifests.push(root.join(CONFIG_PATH));
+        }
+        dirs.sort();
+        dirs.dedup();
+        manifests.sort();
+        manifests.dedup();
+
+        Self {
+            dirs,
+            manifests,
+            target_dir: workspace.root.join(TARGET_DIR),
+            repo: Repository::discover(&workspace.root).ok(),
+        }
+    }
+
+    /// Existing paths to watch
+    ///
+    /// Manifests are watched through their directory, as editors often
+    /// replace a file when saving it.
+    fn watch_paths(&self) -> Vec<(PathBuf, RecursiveMode)> {
+        let dirs = self
+            .dirs
+            .iter()
+            .filter(|dir| dir.is_dir())
+            .map(|dir| (dir.clone(), RecursiveMode::Recursive));
+        let manifest_dirs = self
+            .manifests
+            .iter()
+            .filter_map(|manifest| manifest.parent())
+            .map(|dir| (dir.to_owned(), RecursiveMode::NonRecursive));
+
+        dirs.chain(manifest_dirs).collect()
+    }
+
+    fn is_relevant(&self, event: &Event) -> bool {
+        let changes_contents = !matches!(
+            event.kind,
+            EventKind::Access(_)
+                | EventKind::Modify(ModifyKind::Metadata(MetadataKind::AccessTime))
+        );
+
+        changes_contents && event.paths.iter().any(|path| self.is_watched(path))
+    }
+
+    fn is_watched(&self, path: &Path) -> bool {
+        if path.starts_with(&self.target_dir) {
+            return false;
+        }
+        if !self.manifests.iter().any(|manifest| manifest == path)
+            && !self.dirs.iter().any(|dir| path.starts_with(dir))
+        {
+            return false;
+        }
+
+        !self.is_ignored(path)
+    }
+
+    /// Whether the repository's `.gitignore` files cover `path`
+    fn is_ignored(&self, path: &Path) -> bool {
+        let repo = match &self.repo {
+            Some(repo) => repo,
+            None => return false,
+        };
+        let relative = match repo.workdir().and_then(|dir| path.strip_prefix(dir).ok()) {
+            Some(relative) => relative,
+            None => return false,
+        };
+
+        repo.is_path_ignored(relative).unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{file::normalize_path, test::testdir_project};
+
+    use notify::event::{AccessKind, CreateKind, DataChange};
+
+    use std::{fs, num::NonZeroUsize};
+
+    #[test]
+    fn crame_args_for_action() {
+        let options = BuildOptions {
+            jobs: NonZeroUsize::new(4),
+            profile: Profile::Release,
+        };
+
+        let args = crame_args(WatchAction::Run, &options, false);
+
+        assert_eq!(vec!["build", "--release", "--jobs", "4"], args);
+        assert_eq!(
+            vec!["test"],
+            crame_args(WatchAction::Test, &BuildOptions::default(), false)
+        );
+        assert_eq!(
+            vec!["test", "--message-format", "json"],
+            crame_args(WatchAction::Test, &BuildOptions::default(), true)
+        );
+    }
+
+    #[test]
+    fn filter_watches_sources_and_manifest() {
+        let (_dir, path) = testdir_project();
+        let workspace = Workspace::load(&path).expect("load workspace");
+        let root = normalize_path(&path).expect("normalize");
+        let filter = ChangeFilter::new(&workspace);
+
+        assert!(filter.is_relevant(&modified(root.join("src").join("main.c"))));
+        assert!(filter.is_relevant(&modified(root.join("tests").join("unit").join("a.c"))));
+        assert!(filter.is_relevant(&modified(root.join(CONFIG_PATH))));
+        assert!(!filter.is_relevant(&modified(root.join("compile_commands.json"))));
+        assert!(!filter.is_relevant(&modified(root.join("target").join("debug").join("test"))));
+    }
+
+    #[test]
+    fn filter_ignores_reads() {
+        let (_dir, path) = testdir_project();
+        let workspace = Workspace::load(&path).expect("load workspace");
+        let root = normalize_path(&path).expect("normalize");
+        let filter = ChangeFilter::new(&workspace);
+
+        let event = Event::new(EventKind::Access(AccessKind::Any))
+            .add_path(root.join("src").join("main.c"));
+
+        assert!(!filter.is_relevant(&event));
+    }
+
+    #[test]
+    fn filter_ignores_gitignored_files() {
+        let (_dir, path) = testdir_project();
+        Repository::init(&path).expect("init repo");
+        fs::write(path.join(".gitignore"), "src/generated.c\n").expect("write gitignore");
+        let workspace = Workspace::load(&path).expect("load workspace");
+        let root = normalize_path(&path).expect("normalize");
+        let filter = ChangeFilter::new(&workspace);
+
+        let created = Event::new(EventKind::Create(CreateKind::File))
+            .add_path(root.join("src").join("generated.c"));
+
+        assert!(!filter.is_relevant(&created));
+        assert!(filter.is_relevant(&modified(root.join("src").join("main.c"))));
+    }
+
+    fn modified(path: PathBuf) -> Event {
+        Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Content))).add_path(path)
+    }
+}
@@ -0,0 +1,354 @@
+use super::{
+    build::{BuildContext, BuildOptions},
+    config::{Config, LibType, Manifest},
+};
+use crate::util::{
+    error::Error,
+    file::{normalize_path, relative_path},
+};
+
+use std::path::{Path, PathBuf};
+
+/// Packages built together into one `target` directory
+///
+/// A package manifest gives a workspace of just that package, and a
+/// workspace manifest one of each of its members. The path dependencies of
+/// every package are included, even if they are not members.
+#[derive(Debug)]
+pub struct Workspace {
+    /// Directory containing the `target` directory
+    pub root: PathBuf,
+    /// Packages in dependency order, each after the packages it depends on
+    pub packages: Vec<Package>,
+}
+
+#[derive(Debug)]
+pub struct Package {
+    /// Normalized package directory
+    pub root: PathBuf,
+    pub config: Config,
+    /// Whether the package is selected by the root manifest, rather than only
+    /// being a dependency
+    pub member: bool,
+    /// Indices of direct dependencies in [`Workspace::packages`]
+    dependencies: Vec<usize>,
+}
+
+impl Workspace {
+    /// Load the workspace described by the `Crame.toml` in `root`
+    #[tracing::instrument(level = "debug")]
+    pub fn load(root: &Path) -> anyhow::Result<Self> {
+        let root = normalize_path(root)?;
+
+        let members = match Manifest::load_from_dir(&mut root.clone())? {
+            Manifest::Package(config) => vec![(root.clone(), Some(*config))],
+            Manifest::Workspace(workspace) => workspace
+                .members
+                .iter()
+                .map(|member| (root.join(member), None))
+                .collect(),
+        };
+
+        let mut loader = Loader::default();
+        for (path, config) in members {
+            loader.load(&path, config, true)?;
+        }
+
+        tracing::debug!(packages = loader.packages.len(), "Loaded workspace");
+
+        Ok(Self {
+            root,
+            packages: loader.packages,
+        })
+    }
+
+    /// Load the workspace of the nearest `Crame.toml` in `dir` or its parents
+    pub fn find(dir: &Path) -> anyhow::Result<Self> {
+        Self::load(&Manifest::find_root(dir)?)
+    }
+
+    /// Packages selected by the root manifest
+    pub fn members(&self) -> impl Iterator<Item = &Package> {
+        self.packages.iter().filter(|package| package.member)
+    }
+
+    /// Build context of `package`, writing into the workspace's `target`
+    /// directory and using the headers and libraries of its dependencies
+    pub fn context(&self, package: &Package, options: &BuildOptions) -> BuildContext {
+        let mut context = BuildContext::new(&package.root, &package.config, options);
+
+        if package.root != self.root {
+            let root_dir = relative_path(&package.root, &self.root);
+            context.share_out_dir(&root_dir, &package.config.package.name);
+        }
+
+        let mut links_shared = false;
+        for dependency in self.dependencies(package) {
+            let dir = relative_path(&package.root, &dependency.root);
+            for include_dir in &dependency.config.build.include_dirs {
+                context.compiler = context.compiler.include_dir(dir.join(include_dir));
+            }
+
+            let lib = dependency.config.lib.as_ref().expect("checked when loaded");
+            let name = lib
+                .name
+                .as_deref()
+                .unwrap_or(&dependency.config.package.name);
+            let lib_type = if lib.types.contains(&LibType::Static) {
+                LibType::Static
+            } else {
+                LibType::Shared
+            };
+
+            context.libraries.push(context.library_path(name, lib_type));
+            links_shared |= lib_type == LibType::Shared;
+        }
+
+        // shared libraries are built next to the executables linking them
+        if links_shared {
+            context.compiler = context.compiler.rpath_origin();
+        }
+
+        context
+    }
+
+    /// Direct and indirect dependencies of `package`, each before the
+    /// packages it depends on, as needed when linking static libraries
+    fn dependencies(&self, package: &Package) -> Vec<&Package> {
+        let mut indices = Vec::new();
+        let mut pending = package.dependencies.clone();
+
+        while let Some(index) = pending.pop() {
+            if !indices.contains(&index) {
+                indices.push(index);
+                pending.extend(&self.packages[index].dependencies);
+            }
+        }
+
+        // packages are in dependency order, so reversing it puts dependents first
+        indices.sort_unstable_by(|a, b| b.cmp(a));
+        indices.iter().map(|&index| &self.packages[index]).collect()
+    }
+}
+
+#[derive(Default)]
+struct Loader {
+    packages: Vec<Package>,
+    /// Packages being loaded, to detect cycles
+    stack: Vec<PathBuf>,
+}
+
+impl Loader {
+    /// Load the package at `path` after its dependencies, returning its index
+    fn load(&mut self, path: &Path, config: Option<Config>, member: bool) -> anyhow::Result<usize> {
+        let root = normalize_path(path)?;
+
+        if let Some(index) = self
+            .packages
+            .iter()
+            .position(|package| package.root == root)
+        {
+            self.packages[index].member |= member;
+            return Ok(index);
+        }
+
+        let config = match config {
+            Some(config) => config,
+            None => Config::load_from_dir(&mut root.clone())?,
+        };
+
+        if self.stack.contains(&root) {
+            anyhow::bail!(Error::DependencyCycle(config.package.name));
+        }
+
+        self.stack.push(root.clone());
+
+        let mut dependencies = Vec::new();
+        for (name, dependency) in &config.dependencies {
+            let index = self.load(&root.join(&dependency.path), None, false)?;
+
+            let has_lib = self.packages[index]
+                .config
+                .lib
+                .as_ref()
+                .is_some_and(|lib| !lib.types.is_empty());
+            if !has_lib {
+                anyhow::bail!(Error::NoLib(name.to_owned()));
+            }
+
+            dependencies.push(index);
+        }
+
+        self.stack.pop();
+
+        self.packages.push(Package {
+            root,
+            config,
+            member,
+            dependencies,
+        });
+
+        Ok(self.packages.len() - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        service::{build::build_workspace, config::Dependency},
+        util::test::{testdir_project, testdir_workspace},
+    };
+
+    use std::fs;
+
+    #[test]
+    fn load_package_manifest() {
+        let (_dir, path) = testdir_project();
+
+        let workspace = Workspace::load(&path).expect("load workspace");
+
+        assert_eq!(normalize_path(&path).unwrap(), workspace.root);
+        assert_eq!(1, workspace.members().count());
+    }
+
+    #[test]
+    fn find_from_subdirectory() {
+        let (_dir, path) = testdir_project();
+
+        let workspace = Workspace::find(&path.join("src")).expect("find workspace");
+
+        assert_eq!(normalize_path(&path).unwrap(), workspace.root);
+    }
+
+    #[test]
+    fn load_members_in_dependency_order() {
+        let (_dir, path) = testdir_workspace();
+
+        let workspace = Workspace::load(&path).expect("load workspace");
+
+        let names: Vec<_> = workspace
+            .packages
+            .iter()
+            .map(|package| package.config.package.name.as_str())
+            .collect();
+        assert_eq!(vec!["mylib", "tools"], names);
+    }
+
+    #[test]
+    fn context_uses_shared_target_and_dependency() {
+        let (_dir, path) = testdir_workspace();
+        let workspace = Workspace::load(&path).expect("load workspace");
+        let tools = &workspace.packages[1];
+
+        let context = workspace.context(tools, &BuildOptions::default());
+
+        assert_eq!(PathBuf::from("../target/debug"), context.out_dir);
+        assert_eq!(
+            PathBuf::from("../target/debug/obj/tools"),
+            context.obj_dir()
+        );
+        assert_eq!(
+            vec![PathBuf::from("../target/debug/libmylib.a")],
+            context.libraries
+        );
+        let invocation = context.compiler.compile(
+            Path::new("src/main.c"),
+            Path::new("main.o"),
+            Path::new("main.d"),
+        );
+        let args = invocation.command_line();
+        assert!(args.contains(&"-I../mylib/include".into()));
+    }
+
+    #[test]
+    fn load_dependency_without_lib_error() {
+        let (_dir, path) = testdir_workspace();
+        let mut config = Config::load_from_dir(&mut path.join("mylib")).expect("load config");
+        config.lib = None;
+        config
+            .save_in_dir(&mut path.join("mylib"))
+            .expect("save config");
+
+        let err = Workspace::load(&path).expect_err("no lib");
+
+        let err = err.downcast_ref::<Error>().expect("crame error");
+        assert!(matches!(err, Error::NoLib(_)));
+    }
+
+    #[test]
+    fn load_dependency_cycle_error() {
+        let (_dir, path) = testdir_workspace();
+        let mut config = Config::load_from_dir(&mut path.join("mylib")).expect("load config");
+        add_dependency(&mut config, "tools", "../tools");
+        config
+            .save_in_dir(&mut path.join("mylib"))
+            .expect("save config");
+
+        let err = Workspace::load(&path).expect_err("cycle");
+
+        let err = err.downcast_ref::<Error>().expect("crame error");
+        assert!(matches!(err, Error::DependencyCycle(_)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn build_workspace_links_dependency() {
+        let (_dir, path) = testdir_workspace();
+        let main = "#include \"mylib/mylib.h\"\nint main(void) { return mylib_add(2, 3); }\n";
+        fs::write(path.join("tools").join("src").join("main.c"), main).expect("write main");
+        let workspace = Workspace::load(&path).expect("load workspace");
+
+        let artifacts =
+            build_workspace(&workspace, &BuildOptions::default()).expect("build workspace");
+
+        let exe = path.join("tools").join(&artifacts.executables["tools"]);
+        let status = std::process::Command::new(exe).status().expect("run tools");
+        assert_eq!(Some(5), status.code());
+        assert!(path
+            .join("target")
+            .join("debug")
+            .join("libmylib.a")
+            .exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn build_workspace_runs_with_shared_dependency() {
+        let (_dir, path) = testdir_workspace();
+        let mut config = Config::load_from_dir(&mut path.join("mylib")).expect("load config");
+        config.lib.as_mut().expect("lib table").types = vec![LibType::Shared];
+        config
+            .save_in_dir(&mut path.join("mylib"))
+            .expect("save config");
+        let main = "#include \"mylib/mylib.h\"\nint main(void) { return mylib_add(2, 3); }\n";
+        fs::write(path.join("tools").join("src").join("main.c"), main).expect("write main");
+        let workspace = Workspace::load(&path).expect("load workspace");
+
+        let artifacts =
+            build_workspace(&workspace, &BuildOptions::default()).expect("build workspace");
+
+        let exe = path.join("tools").join(&artifacts.executables["tools"]);
+        let status = std::process::Command::new(exe).status().expect("run tools");
+        assert_eq!(Some(5), status.code());
+    }
+
+    #[test]
+    fn build_workspace_duplicate_bin_error() {
+        let (_dir, path) = testdir_workspace();
+        let bin_dir = path.join("mylib").join("src").join("bin");
+        fs::create_dir_all(&bin_dir).expect("create bin dir");
+        fs::write(bin_dir.join("tools.c"), "int main(void) { return 0; }\n").expect("write bin");
+        let workspace = Workspace::load(&path).expect("load workspace");
+
+        let err = build_workspace(&workspace, &BuildOptions::default()).expect_err("duplicate");
+
+        let err = err.downcast_ref::<Error>().expect("crame error");
+        assert!(matches!(err, Error::DuplicateBin(..)));
+    }
+
+    fn add_dependency(config: &mut Config, name: &str, path: &str) {
+        let dependency = Dependency { path: path.into() };
+        config.dependencies.insert(name.to_owned(), dependency);
+    }
+}
@@ -1,9 +1,24 @@
 use std::{io, path::PathBuf, process};
 
 use colored::Colorize;
+use serde::Serialize;
+
+use super::message;
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
+    #[error("`{0}` has multiple executables, choose one with `--bin`")]
+    AmbiguousBin(String),
+
+    #[error("could not compile `{0}`")]
+    Build(String),
+
+    #[error("unable to clone template: `{0}`")]
+    Clone(String),
+
+    #[error("unable to run compiler: `{0}`")]
+    Compiler(String),
+
     #[error("invalid config file: `{0}`")]
     Config(PathBuf),
 
@@ -16,18 +31,128 @@ pub enum Error {
     #[error("`{0}` already exists")]
     Conflict(PathBuf),
 
+    #[error("dependency cycle involving `{0}`")]
+    DependencyCycle(String),
+
+    #[error("`{1}` and `{2}` both have an executable named `{0}`")]
+    DuplicateBin(String, String, String),
+
+    #[error("unable to execute: `{0}`")]
+    Execute(PathBuf),
+
+    #[error("invalid module path: `{0}`, expected a path within `src` such as `util/str`")]
+    InvalidModule(PathBuf),
+
+    #[error("no module at `src/{0}`")]
+    NoModule(PathBuf),
+
+    #[error("no executable named `{0}`")]
+    NoBin(String),
+
+    #[error("dependency `{0}` has no library target")]
+    NoLib(String),
+
+    #[error("no template `{0}`, expected a directory, a git repository, or a template in {1}")]
+    NoTemplate(String, String),
+
+    #[error("not inside a crame project, no `Crame.toml` in `{0}` or any parent directory")]
+    NoProject(PathBuf),
+
+    #[error("`{0}` is a workspace, run this command inside one of its packages")]
+    NotPackage(PathBuf),
+
     #[error("unable to normalize path: `{0}`")]
     Normalize(PathBuf),
 
+    #[error("unable to read directory: `{0}`")]
+    ReadDir(PathBuf),
+
     #[error("unable to read file: `{0}`")]
     ReadFile(PathBuf),
 
+    #[error("unable to remove file: `{0}`")]
+    RemoveFile(PathBuf),
+
+    #[error("test failed for `{0}`")]
+    TestFailed(String),
+
+    #[error("unable to watch for changes: `{0}`")]
+    Watch(PathBuf),
+
     #[error("cannot write to file: `{0}`")]
     WriteFile(PathBuf),
 }
 
+/// Exit code when tests ran but did not all pass
+pub const TEST_FAILURE: exitcode::ExitCode = 1;
+
+/// Exit code when the project failed to compile
+///
+/// Distinct from the sysexits codes, so it is not confused with the exit code
+/// of a program run by crame.
+pub const BUILD_FAILURE: exitcode::ExitCode = 101;
+
+/// Exit code when no `Crame.toml` was found for a command that needs one
+pub const NO_PROJECT: exitcode::ExitCode = 102;
+
+/// Error printed with `--message-format json`
+#[derive(Debug, Serialize)]
+#[serde(tag = "reason", rename = "error")]
+struct ErrorMessage {
+    /// Name of the [`Error`] variant, if it is one
+    error: Option<&'static str>,
+    message: String,
+    causes: Vec<String>,
+    exit_code: exitcode::ExitCode,
+}
+
+impl ErrorMessage {
+    fn new(err: &anyhow::Error) -> Self {
+        Self {
+            error: err.downcast_ref::<Error>().map(Error::name),
+            message: err.to_string(),
+            causes: err.chain().skip(1).map(ToString::to_string).collect(),
+            exit_code: exitcode_from_err(err),
+        }
+    }
+}
+
+impl Error {
+    /// Name of the variant
+    pub fn name(&self) -> &'static str {
+        match self {
+            Error::AmbiguousBin(_) => "AmbiguousBin",
+            Error::Build(_) => "Build",
+            Error::Clone(_) => "Clone",
+            Error::Compiler(_) => "Compiler",
+            Error::Config(_) => "Config",
+            Error::CreateDir(_) => "CreateDir",
+            Error::CreateFile(_) => "CreateFile",
+            Error::Conflict(_) => "Conflict",
+            Error::DependencyCycle(_) => "DependencyCycle",
+            Error::DuplicateBin(..) => "DuplicateBin",
+            Error::Execute(_) => "Execute",
+            Error::InvalidModule(_) => "InvalidModule",
+            Error::NoModule(_) => "NoModule",
+            Error::NoBin(_) => "NoBin",
+            Error::NoLib(_) => "NoLib",
+            Error::NoTemplate(..) => "NoTemplate",
+            Error::NoProject(_) => "NoProject",
+            Error::NotPackage(_) => "NotPackage",
+            Error::Normalize(_) => "Normalize",
+            Error::ReadDir(_) => "ReadDir",
+            Error::ReadFile(_) => "ReadFile",
+            Error::RemoveFile(_) => "RemoveFile",
+            Error::TestFailed(_) => "TestFailed",
+            Error::Watch(_) => "Watch",
+            Error::WriteFile(_) => "WriteFile",
+        }
+    }
+}
+
 pub fn report_exit(err: &anyhow::Error, verbose: bool) {
     report(err, verbose);
+    message::emit(&ErrorMessage::new(err));
     process::exit(exitcode_from_err(err));
 }
 
@@ -52,9 +177,21 @@ fn exitcode_from_err(err: &anyhow::Error) -> exitcode::ExitCode {
             | Error::CreateDir(_)
             | Error::Normalize(_)
             | Error::CreateFile(_) => exitcode::CANTCREAT,
-            Error::WriteFile(_) => exitcode::IOERR,
-            Error::ReadFile(_) => exitcode::NOINPUT,
-            Error::Config { .. } => exitcode::CONFIG,
+            Error::RemoveFile(_) | Error::Watch(_) | Error::WriteFile(_) => exitcode::IOERR,
+            Error::NoTemplate(..) | Error::ReadDir(_) | Error::ReadFile(_) => exitcode::NOINPUT,
+            Error::Build(_) => BUILD_FAILURE,
+            Error::NoProject(_) => NO_PROJECT,
+            Error::AmbiguousBin(_)
+            | Error::InvalidModule(_)
+            | Error::NoBin(_)
+            | Error::NoModule(_)
+            | Error::NotPackage(_) => exitcode::USAGE,
+            Error::Clone(_) | Error::Compiler(_) | Error::Execute(_) => exitcode::UNAVAILABLE,
+            Error::TestFailed(_) => TEST_FAILURE,
+            Error::Config { .. }
+            | Error::DependencyCycle(_)
+            | Error::DuplicateBin(..)
+            | Error::NoLib(_) => exitcode::CONFIG,
         }
     } else if let Some(err) = err.downcast_ref::<io::Error>() {
         match err.kind() {
@@ -72,17 +209,64 @@ fn exitcode_from_err(err: &anyhow::Error) -> exitcode::ExitCode {
 mod tests {
     use super::*;
 
+    #[test]
+    fn error_message_json() {
+        let err = anyhow::Error::new(io::Error::other("disk full"))
+            .context(Error::WriteFile(PathBuf::from("out")));
+
+        let json = serde_json::to_value(ErrorMessage::new(&err)).expect("serialize");
+
+        let expected = serde_json::json!({
+            "reason": "error",
+            "error": "WriteFile",
+            "message": "cannot write to file: `out`",
+            "causes": ["disk full"],
+            "exit_code": exitcode::IOERR,
+        });
+        assert_eq!(expected, json);
+    }
+
     #[test]
     fn exitcode_generic_error() {
         let code = exitcode_from_err(&anyhow::anyhow!("test error"));
         assert_eq!(exitcode::SOFTWARE, code);
     }
 
+    #[test]
+    fn exitcode_ambiguous_bin() {
+        assert_error_code(Error::AmbiguousBin(String::new()), exitcode::USAGE);
+    }
+
+    #[test]
+    fn exitcode_build() {
+        assert_error_code(Error::Build(String::new()), BUILD_FAILURE);
+    }
+
+    #[test]
+    fn exitcode_clone() {
+        assert_error_code(Error::Clone(String::new()), exitcode::UNAVAILABLE);
+    }
+
+    #[test]
+    fn exitcode_compiler() {
+        assert_error_code(Error::Compiler(String::new()), exitcode::UNAVAILABLE);
+    }
+
     #[test]
     fn exitcode_conflict() {
         assert_error_code(Error::Conflict(PathBuf::new()), exitcode::CANTCREAT);
     }
 
+    #[test]
+    fn exitcode_dependency_cycle() {
+        assert_error_code(Error::DependencyCycle(String::new()), exitcode::CONFIG);
+    }
+
+    #[test]
+    fn exitcode_execute() {
+        assert_error_code(Error::Execute(PathBuf::new()), exitcode::UNAVAILABLE);
+    }
+
     #[test]
     fn exitcode_create_dir() {
         assert_error_code(Error::CreateDir(PathBuf::new()), exitcode::CANTCREAT);
@@ -93,11 +277,54 @@ mod tests {
         assert_error_code(Error::CreateFile(PathBuf::new()), exitcode::CANTCREAT);
     }
 
+    #[test]
+    fn exitcode_invalid_module() {
+        assert_error_code(Error::InvalidModule(PathBuf::new()), exitcode::USAGE);
+    }
+
+    #[test]
+    fn exitcode_no_bin() {
+        assert_error_code(Error::NoBin(String::new()), exitcode::USAGE);
+    }
+
+    #[test]
+    fn exitcode_no_module() {
+        assert_error_code(Error::NoModule(PathBuf::new()), exitcode::USAGE);
+    }
+
+    #[test]
+    fn exitcode_no_lib() {
+        assert_error_code(Error::NoLib(String::new()), exitcode::CONFIG);
+    }
+
+    #[test]
+    fn exitcode_no_template() {
+        assert_error_code(
+            Error::NoTemplate(String::new(), String::new()),
+            exitcode::NOINPUT,
+        );
+    }
+
+    #[test]
+    fn exitcode_no_project() {
+        assert_error_code(Error::NoProject(PathBuf::new()), NO_PROJECT);
+    }
+
+    #[test]
+    fn exitcode_not_package() {
+        assert_error_code(Error::NotPackage(PathBuf::new()), exitcode::USAGE);
+    }
+
     #[test]
     fn exitcode_normalize() {
         assert_error_code(Error::Normalize(PathBuf::new()), exitcode::CANTCREAT);
     }
 
+    #[test]
+    fn exitcode_watch() {
+        assert_error_code(Error::Watch(PathBuf::new()), exitcode::IOERR);
+    }
+
     #[test]
     fn exitcode_write_file() {
         assert_error_code(Error::WriteFile(PathBuf::new()), exitcode::IOERR);
@@ -108,11 +335,26 @@ mod tests {
         assert_error_code(Error::ReadFile(PathBuf::new()), exitcode::NOINPUT);
     }
 
+    #[test]
+    fn exitcode_remove_file() {
+        assert_error_code(Error::RemoveFile(PathBuf::new()), exitcode::IOERR);
+    }
+
+    #[test]
+    fn exitcode_read_dir() {
+        assert_error_code(Error::ReadDir(PathBuf::new()), exitcode::NOINPUT);
+    }
+
     #[test]
     fn exitcode_config() {
         assert_error_code(Error::Config(PathBuf::new()), exitcode::CONFIG);
     }
 
+    #[test]
+    fn exitcode_test_failed() {
+        assert_error_code(Error::TestFailed(String::new()), TEST_FAILURE);
+    }
+
     #[test]
     fn exitcode_generic_io_error() {
         assert_io_error_code(io::ErrorKind::BrokenPipe, exitcode::IOERR);
@@ -1,14 +1,33 @@
 use std::{
     fs,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
 };
 
 use anyhow::Context;
 
 use super::error::Error;
 
+pub fn current_dir() -> anyhow::Result<PathBuf> {
+    std::env::current_dir().context("unable to locate current directory")
+}
+
+/// Per-user configuration directory, such as `~/.config` on Linux
+pub fn config_dir() -> Option<PathBuf> {
+    let var = |name| std::env::var_os(name).filter(|value| !value.is_empty());
+
+    if cfg!(windows) {
+        var("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        var("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        var("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| var("HOME").map(|home| PathBuf::from(home).join(".config")))
+    }
+}
+
 pub fn absolute_path(path: &Path) -> anyhow::Result<PathBuf> {
-    let mut output = std::env::current_dir().context("unable to locate current directory")?;
+    let mut output = current_dir()?;
     output.push(path);
 
     Ok(output)
@@ -26,3 +45,126 @@ pub fn normalize_path(path: &Path) -> anyhow::Result<PathBuf> {
     path.canonicalize()
         .with_context(|| Error::Normalize(path.to_owned()))
 }
+
+/// Path to `to` relative to the directory `from`
+///
+/// Both paths should be absolute. `..` components are resolved without
+/// accessing the filesystem, so neither path needs to exist.
+pub fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let from = lexical_normalize(from);
+    let to = lexical_normalize(to);
+
+    let common = from
+        .components()
+        .zip(to.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut relative = PathBuf::new();
+    for _ in from.components().skip(common) {
+        relative.push("..");
+    }
+    relative.extend(to.components().skip(common));
+
+    relative
+}
+
+/// Resolve `.` and `..` components without accessing the filesystem
+pub fn lexical_normalize(path: &Path) -> PathBuf {
+    let mut normal = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normal.pop();
+            }
+            _ => normal.push(component),
+        }
+    }
+
+    normal
+}
+
+/// Recursively find all files in `dir` with the given extension
+///
+/// Returned paths are prefixed with `dir` and sorted. A missing directory is
+/// treated as empty.
+pub fn find_files(dir: &Path, extension: &str) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    if dir.is_dir() {
+        find_files_recursive(dir, extension, &mut files)?;
+    }
+
+    files.sort();
+
+    Ok(files)
+}
+
+fn find_files_recursive(
+    dir: &Path,
+    extension: &str,
+    files: &mut Vec<PathBuf>,
+) -> anyhow::Result<()> {
+    let entries = fs::read_dir(dir).with_context(|| Error::ReadDir(dir.to_owned()))?;
+
+    for entry in entries {
+        let path = entry
+            .with_context(|| Error::ReadDir(dir.to_owned()))?
+            .path();
+
+        if path.is_dir() {
+            find_files_recursive(&path, extension, files)?;
+        } else if path.extension().is_some_and(|ext| ext == extension) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::test::testdir_and_path;
+
+    #[test]
+    fn find_files_missing_dir() {
+        let (_dir, path) = testdir_and_path();
+        let files = find_files(&path.join("missing"), "c").expect("find files");
+        assert!(files.is_empty());
+    }
+
+    #[test]
+    fn find_files_nested_sorted() {
+        let (_dir, path) = testdir_and_path();
+        fs::create_dir_all(path.join("b")).expect("create subdir");
+        for file in ["b/two.c", "a.c", "b/three.h"] {
+            fs::write(path.join(file), "").expect("write file");
+        }
+
+        let files = find_files(&path, "c").expect("find files");
+
+        assert_eq!(vec![path.join("a.c"), path.join("b").join("two.c")], files);
+    }
+
+    #[test]
+    fn relative_path_sibling() {
+        let relative = relative_path(Path::new("/ws/tools"), Path::new("/ws/lib/include"));
+        assert_eq!(PathBuf::from("../lib/include"), relative);
+    }
+
+    #[test]
+    fn relative_path_same_dir() {
+        let relative = relative_path(Path::new("/ws"), Path::new("/ws"));
+        assert_eq!(PathBuf::new(), relative);
+    }
+
+    #[test]
+    fn relative_path_resolves_parent_dirs() {
+        let from = Path::new("/ws/tools/../target/debug/obj/tools");
+        let relative = relative_path(from, Path::new("/ws/tools"));
+        assert_eq!(PathBuf::from("../../../../tools"), relative);
+    }
+}
@@ -0,0 +1,42 @@
+use clap::ValueEnum;
+use serde::Serialize;
+
+use std::{
+    io::{self, Write},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// How crame reports its progress
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MessageFormat {
+    /// Log lines for people, on stderr
+    #[default]
+    Human,
+    /// A JSON object per line on stdout, with the kind of message in `reason`
+    Json,
+}
+
+static JSON: AtomicBool = AtomicBool::new(false);
+
+pub fn set_format(format: MessageFormat) {
+    JSON.store(format == MessageFormat::Json, Ordering::Relaxed);
+}
+
+/// Whether messages are printed as JSON, so stdout is reserved for them
+pub fn is_json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// Print `message` as a line of JSON on stdout, if that format was chosen
+pub fn emit<T: Serialize>(message: &T) {
+    if !is_json() {
+        return;
+    }
+
+    // messages are best effort, like the logs they replace
+    if let Ok(line) = serde_json::to_string(message) {
+        let mut stdout = io::stdout().lock();
+        let _ = writeln!(stdout, "{}", line);
+        let _ = stdout.flush();
+    }
+}
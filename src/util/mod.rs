@@ -1,5 +1,7 @@
 pub mod error;
 pub mod file;
+pub mod message;
+pub mod parallel;
 
 #[cfg(test)]
 pub mod test;
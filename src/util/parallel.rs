@@ -0,0 +1,68 @@
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+/// Number of jobs to run at once when not otherwise configured
+pub fn default_jobs() -> NonZeroUsize {
+    thread::available_parallelism().unwrap_or(NonZeroUsize::MIN)
+}
+
+/// Apply `f` to every item using up to `jobs` threads
+///
+/// Results are returned in the same order as `items`.
+pub fn map<T, R, F>(items: &[T], jobs: NonZeroUsize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(items.len()));
+    let threads = jobs.get().min(items.len());
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let item = match items.get(index) {
+                    Some(item) => item,
+                    None => break,
+                };
+
+                let result = f(item);
+                results.lock().expect("results lock").push((index, result));
+            });
+        }
+    });
+
+    let mut results = results.into_inner().expect("results lock");
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_keeps_order() {
+        let items: Vec<usize> = (0..100).collect();
+        let jobs = NonZeroUsize::new(8).expect("non zero");
+
+        let results = map(&items, jobs, |item| item * 2);
+
+        let expected: Vec<usize> = (0..100).map(|item| item * 2).collect();
+        assert_eq!(expected, results);
+    }
+
+    #[test]
+    fn map_empty() {
+        let results = map(&[] as &[usize], default_jobs(), |item| *item);
+        assert!(results.is_empty());
+    }
+}
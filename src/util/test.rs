@@ -1,7 +1,16 @@
-use std::path::PathBuf;
+use std::{fs, path::PathBuf};
 
 use tempfile::{tempdir, TempDir};
 
+use crate::service::{
+    build::{BuildContext, BuildOptions},
+    build_system::BuildSystem,
+    config::{Config, Dependency},
+    init::{project_init, ProjectKind},
+    test::{build_tests, UNIT_DIR},
+    vcs::VersionControl,
+};
+
 pub fn testdir_and_path() -> (TempDir, PathBuf) {
     let dir = tempdir().expect("create temporary directory");
     let path = dir.path().to_owned();
@@ -16,3 +25,60 @@ pub fn template_dir() -> PathBuf {
     dir.push("template");
     dir
 }
+
+/// Temporary directory containing a freshly initialized crame project
+pub fn testdir_project() -> (TempDir, PathBuf) {
+    testdir_project_kind(ProjectKind::Bin)
+}
+
+/// Temporary directory containing a new project whose unit test `file` has
+/// `source`, with its path, config and built test executable
+pub fn build_unit_tests(file: &str, source: &str) -> (TempDir, PathBuf, Config, PathBuf) {
+    let (dir, mut path) = testdir_project();
+    let config = Config::load_from_dir(&mut path).expect("load config");
+    fs::write(path.join(UNIT_DIR).join(file), source).expect("write test");
+    let context = BuildContext::new(&path, &config, &BuildOptions::default());
+    let exe = build_tests(&context, &config).expect("build tests");
+    (dir, path, config, exe)
+}
+
+/// Temporary directory containing a freshly initialized project of `kind`
+pub fn testdir_project_kind(kind: ProjectKind) -> (TempDir, PathBuf) {
+    let (dir, path) = testdir_and_path();
+    project_init(
+        path.clone(),
+        BuildSystem::Just,
+        VersionControl::None,
+        kind,
+        None,
+    )
+    .expect("init project");
+    (dir, path)
+}
+
+/// Temporary directory containing a workspace of a `mylib` library and a
+/// `tools` executable depending on it
+pub fn testdir_workspace() -> (TempDir, PathBuf) {
+    let (dir, path) = testdir_and_path();
+
+    for (name, kind) in [("mylib", ProjectKind::Lib), ("tools", ProjectKind::Bin)] {
+        let member = path.join(name);
+        fs::create_dir(&member).expect("create member dir");
+        project_init(member, BuildSystem::Just, VersionControl::None, kind, None)
+            .expect("init member");
+    }
+
+    let mut config = Config::load_from_dir(&mut path.join("tools")).expect("load config");
+    let dependency = Dependency {
+        path: "../mylib".into(),
+    };
+    config.dependencies.insert("mylib".to_owned(), dependency);
+    config
+        .save_in_dir(&mut path.join("tools"))
+        .expect("save config");
+
+    let manifest = "[workspace]\nmembers = [\"tools\", \"mylib\"]\n";
+    fs::write(path.join("Crame.toml"), manifest).expect("write manifest");
+
+    (dir, path)
+}
@@ -0,0 +1,6 @@
+#ifndef {{NAME_UPPER}}_H
+#define {{NAME_UPPER}}_H
+
+int {{name_ident}}_add(int a, int b);
+
+#endif
@@ -0,0 +1,5 @@
+#include "{{name}}/{{name}}.h"
+
+int {{name_ident}}_add(int a, int b) {
+    return a + b;
+}
@@ -0,0 +1,10 @@
+#if defined HEADERS
+#include "{{name}}/{{name}}.h"
+#elif defined TESTS
+
+TEST("add returns sum of arguments") {
+    ASSERT_EQ({{name_ident}}_add(0, 0), 0);
+    ASSERT_EQ({{name_ident}}_add(2, 3), 5);
+}
+
+#endif
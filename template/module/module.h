@@ -0,0 +1,4 @@
+#ifndef {{GUARD}}
+#define {{GUARD}}
+
+#endif
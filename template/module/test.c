@@ -0,0 +1,5 @@
+#if defined HEADERS
+#include "{{header}}"
+#elif defined TESTS
+
+#endif
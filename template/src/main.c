@@ -1,5 +1,5 @@
 #include <stdio.h>
 
 int main(void) {
-    printf("Hello, world!\n");
+    printf("Hello from {{name}}!\n");
 }
\ No newline at end of file
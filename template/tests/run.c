@@ -1,36 +1,521 @@
+#include <setjmp.h>
+#include <stdint.h>
 #include <stdio.h>
-#define HEADERS
-#include "./test_all.c"
-#undef HEADERS
+#include <stdlib.h>
+#include <string.h>
+
+#if defined CRAME_EDITOR
+// an editor checking one test file, as set up at the end of this file, leaves
+// most of the framework unused
+#pragma GCC diagnostic ignored "-Wunused-function"
+#pragma GCC diagnostic ignored "-Wunused-variable"
+#endif
+
+// Without arguments every test is run in this process. crame lists the tests
+// with `--list`, then runs each in its own process with `--run <index>`.
+enum crame_mode { CRAME_RUN_ALL, CRAME_LIST, CRAME_RUN_ONE };
+
+static enum crame_mode crame_mode = CRAME_RUN_ALL;
+static int crame_selected = -1;
+static int crame_count = 0;
+
+// options given after the name of a test: TEST("name", .timeout = 5)
+struct crame_test {
+    const char *name;
+    // seconds crame lets the test run, 0 for the timeout in Crame.toml
+    double timeout;
+};
+
+// file crame reads failed assertions from, given in CRAME_TEST_EVENTS
+static FILE *crame_events = NULL;
+
+static jmp_buf crame_jmp;
+static const char *crame_current = "";
+static int crame_test_failed = 0;
+static int crame_passed = 0;
+static int crame_failed = 0;
+
+// function run before or after tests, defined with BEFORE_EACH and the like
+typedef void (*crame_hook)(void);
+
+// whether a test of the test file being included has started, so BEFORE_ALL
+// ran and AFTER_ALL should run once the file ends
+static int crame_file_started = 0;
+
+static int crame_begin(struct crame_test test, const char *file, int line) {
+    int index = crame_count++;
+
+    if (crame_mode == CRAME_LIST) {
+        printf(
+            "%d\t%s\t%d\t%g\t%s\n", index, file, line, test.timeout, test.name
+        );
+        return 0;
+    }
+    if (crame_mode == CRAME_RUN_ONE && index != crame_selected)
+        return 0;
+
+    crame_current = test.name;
+    crame_test_failed = 0;
+    return 1;
+}
+
+static int crame_setup(crame_hook before_all, crame_hook before_each) {
+    if (!crame_file_started) {
+        crame_file_started = 1;
+        if (before_all)
+            before_all();
+    }
+    if (before_each)
+        before_each();
+    return 1;
+}
+
+static int crame_end(crame_hook after_each) {
+    // teardown also runs after a failed assertion, and may fail itself
+    if (after_each) {
+        if (setjmp(crame_jmp) == 0)
+            after_each();
+    }
+
+    if (crame_test_failed)
+        crame_failed++;
+    else
+        crame_passed++;
+    return 0;
+}
+
+static void crame_report(const char *file, int line, const char *assertion) {
+    printf(
+        "!\nTest failed at %s:%d\n    %s: %s\n", file, line, crame_current,
+        assertion
+    );
+    crame_test_failed = 1;
+
+    if (crame_events) {
+        fprintf(crame_events, "fail\t%s\t%d\t%s\n", file, line, assertion);
+        fflush(crame_events);
+    }
+}
+
+static void crame_fail(const char *file, int line, const char *assertion) {
+    crame_report(file, line, assertion);
+    longjmp(crame_jmp, 1);
+}
+
+static void crame_file_end(crame_hook after_all) {
+    if (!crame_file_started)
+        return;
+    crame_file_started = 0;
+
+    crame_test_failed = 0;
+    if (after_all) {
+        if (setjmp(crame_jmp) == 0)
+            after_all();
+    }
+    if (crame_test_failed)
+        crame_failed++;
+}
+
+// hooks of each test file are named after the id the generated test_all.c
+// gives it in CRAME_FILE
+#define CRAME_PASTE_(a, b) a##b
+#define CRAME_PASTE(a, b) CRAME_PASTE_(a, b)
+#define CRAME_HOOK(name) CRAME_PASTE(crame_##name##_, CRAME_FILE)
+
+#define CRAME_DEFINE_HOOK(name)                                                \
+    static void CRAME_PASTE(CRAME_HOOK(name), _run)(void);                     \
+    static crame_hook CRAME_HOOK(name) = CRAME_PASTE(CRAME_HOOK(name), _run);  \
+    static void CRAME_PASTE(CRAME_HOOK(name), _run)(void)
+
+// define the following block as a hook of the tests in this file, in the
+// HEADERS section
+#define BEFORE_ALL CRAME_DEFINE_HOOK(before_all)
+#define AFTER_ALL CRAME_DEFINE_HOOK(after_all)
+#define BEFORE_EACH CRAME_DEFINE_HOOK(before_each)
+#define AFTER_EACH CRAME_DEFINE_HOOK(after_each)
+
+// runs the following block once after the file's hooks, jumping out of it
+// when an assertion fails. The options are volatile, as they live in the frame
+// calling setjmp, which -Wclobbered warns about otherwise.
+#define TEST(...)                                                              \
+    for (int crame_run = crame_begin(                                          \
+             (volatile struct crame_test){.name = __VA_ARGS__}, __FILE__,      \
+             __LINE__                                                          \
+         );                                                                    \
+         crame_run; crame_run = crame_end(CRAME_HOOK(after_each)))             \
+        if (setjmp(crame_jmp) == 0)                                            \
+            if (crame_setup(CRAME_HOOK(before_all), CRAME_HOOK(before_each)))
 
-#define TEST(name) test = name;
 #define ASSERT(ast)                                                            \
     do {                                                                       \
-        assertion = #ast;                                                      \
-        file = __FILE__;                                                       \
-        line = __LINE__;                                                       \
         if (ast)                                                               \
             putchar('.');                                                      \
         else                                                                   \
-            goto fail;                                                         \
+            crame_fail(__FILE__, __LINE__, #ast);                              \
     } while (0)
 
-int main() {
-    const char *test = "";
-    const char *assertion = "";
-    const char *file = "";
-    int line = 0;
+// value of either side of a comparison, kept to print when it fails
+enum crame_kind { CRAME_INT, CRAME_UINT, CRAME_FLOAT, CRAME_PTR };
+
+struct crame_value {
+    enum crame_kind kind;
+    intmax_t i;
+    uintmax_t u;
+    long double f;
+    const void *p;
+};
+
+static struct crame_value crame_int(intmax_t i) {
+    struct crame_value value = {CRAME_INT, 0, 0, 0, NULL};
+    value.i = i;
+    return value;
+}
+
+static struct crame_value crame_uint(uintmax_t u) {
+    struct crame_value value = {CRAME_UINT, 0, 0, 0, NULL};
+    value.u = u;
+    return value;
+}
+
+static struct crame_value crame_float(long double f) {
+    struct crame_value value = {CRAME_FLOAT, 0, 0, 0, NULL};
+    value.f = f;
+    return value;
+}
+
+static struct crame_value crame_ptr(const void *p) {
+    struct crame_value value = {CRAME_PTR, 0, 0, 0, NULL};
+    value.p = p;
+    return value;
+}
+
+#if __STDC_VERSION__ >= 201112L
+#define CRAME_VALUE(x)                                                         \
+    _Generic((x),                                                              \
+        _Bool: crame_uint,                                                     \
+        char: crame_int,                                                       \
+        signed char: crame_int,                                                \
+        short: crame_int,                                                      \
+        int: crame_int,                                                        \
+        long: crame_int,                                                       \
+        long long: crame_int,                                                  \
+        unsigned char: crame_uint,                                             \
+        unsigned short: crame_uint,                                            \
+        unsigned: crame_uint,                                                  \
+        unsigned long: crame_uint,                                             \
+        unsigned long long: crame_uint,                                        \
+        float: crame_float,                                                    \
+        double: crame_float,                                                   \
+        long double: crame_float,                                              \
+        default: crame_ptr)(x)
+#else
+// without _Generic numbers are compared as long double, and pointers with
+// ASSERT_NULL and ASSERT_NOT_NULL only
+#define CRAME_VALUE(x) crame_float(x)
+#endif
+
+static long double crame_to_float(struct crame_value value) {
+    switch (value.kind) {
+    case CRAME_INT:
+        return (long double)value.i;
+    case CRAME_UINT:
+        return (long double)value.u;
+    case CRAME_PTR:
+        return (long double)(uintptr_t)value.p;
+    default:
+        return value.f;
+    }
+}
+
+// -1, 0 or 1 as `left` is less than, equal to or greater than `right`,
+// comparing signed and unsigned integers by their value
+static int crame_compare(struct crame_value left, struct crame_value right) {
+    if (left.kind == CRAME_FLOAT || right.kind == CRAME_FLOAT) {
+        long double l = crame_to_float(left), r = crame_to_float(right);
+        return (l > r) - (l < r);
+    }
+    if (left.kind == CRAME_PTR)
+        left = crame_uint((uintptr_t)left.p);
+    if (right.kind == CRAME_PTR)
+        right = crame_uint((uintptr_t)right.p);
+
+    if (left.kind == CRAME_INT && right.kind == CRAME_INT)
+        return (left.i > right.i) - (left.i < right.i);
+    if (left.kind == CRAME_INT && left.i < 0)
+        return -1;
+    if (right.kind == CRAME_INT && right.i < 0)
+        return 1;
+
+    uintmax_t l = left.kind == CRAME_INT ? (uintmax_t)left.i : left.u;
+    uintmax_t r = right.kind == CRAME_INT ? (uintmax_t)right.i : right.u;
+    return (l > r) - (l < r);
+}
+
+static void crame_print_value(const char *label, struct crame_value value) {
+    printf("    %6s: ", label);
+    switch (value.kind) {
+    case CRAME_INT:
+        printf("%jd\n", value.i);
+        break;
+    case CRAME_UINT:
+        printf("%ju\n", value.u);
+        break;
+    case CRAME_FLOAT:
+        printf("%.*Lg\n", 17, value.f);
+        break;
+    case CRAME_PTR:
+        if (value.p)
+            printf("%p\n", (void *)value.p);
+        else
+            puts("NULL");
+        break;
+    }
+}
 
+enum crame_op { CRAME_EQ, CRAME_NE, CRAME_LT, CRAME_LE, CRAME_GT, CRAME_GE };
+
+static void crame_assert_compare(
+    enum crame_op op, struct crame_value left, struct crame_value right,
+    const char *file, int line, const char *assertion
+) {
+    int order = crame_compare(left, right);
+    int passed = 0;
+
+    switch (op) {
+    case CRAME_EQ:
+        passed = order == 0;
+        break;
+    case CRAME_NE:
+        passed = order != 0;
+        break;
+    case CRAME_LT:
+        passed = order < 0;
+        break;
+    case CRAME_LE:
+        passed = order <= 0;
+        break;
+    case CRAME_GT:
+        passed = order > 0;
+        break;
+    case CRAME_GE:
+        passed = order >= 0;
+        break;
+    }
+
+    if (passed) {
+        putchar('.');
+        return;
+    }
+    crame_report(file, line, assertion);
+    crame_print_value("left", left);
+    crame_print_value("right", right);
+    longjmp(crame_jmp, 1);
+}
+
+static void crame_assert_near(
+    long double left, long double right, long double tolerance,
+    const char *file, int line, const char *assertion
+) {
+    long double difference = left > right ? left - right : right - left;
+
+    if (difference <= tolerance) {
+        putchar('.');
+        return;
+    }
+    crame_report(file, line, assertion);
+    crame_print_value("left", crame_float(left));
+    crame_print_value("right", crame_float(right));
+    crame_print_value("diff", crame_float(difference));
+    longjmp(crame_jmp, 1);
+}
+
+static void crame_print_string(const char *label, const char *value) {
+    if (value)
+        printf("    %6s: \"%s\"\n", label, value);
+    else
+        printf("    %6s: NULL\n", label);
+}
+
+static void crame_assert_str_eq(
+    const char *left, const char *right, const char *file, int line,
+    const char *assertion
+) {
+    int equal = left && right ? strcmp(left, right) == 0 : left == right;
+
+    if (equal) {
+        putchar('.');
+        return;
+    }
+    crame_report(file, line, assertion);
+    crame_print_string("left", left);
+    crame_print_string("right", right);
+    longjmp(crame_jmp, 1);
+}
+
+// prints up to 16 bytes from `offset`, where the memory first differs
+static void crame_print_bytes(
+    const char *label, const unsigned char *bytes, size_t offset, size_t size
+) {
+    size_t end = size - offset > 16 ? offset + 16 : size;
+
+    printf("    %6s: [%zu]", label, offset);
+    for (size_t i = offset; i < end; i++)
+        printf(" %02x", bytes[i]);
+    puts(end < size ? " ..." : "");
+}
+
+static void crame_assert_mem_eq(
+    const void *left, const void *right, size_t size, const char *file,
+    int line, const char *assertion
+) {
+    const unsigned char *l = left, *r = right;
+    size_t offset = 0;
+
+    while (offset < size && l[offset] == r[offset])
+        offset++;
+
+    if (offset == size) {
+        putchar('.');
+        return;
+    }
+    crame_report(file, line, assertion);
+    crame_print_bytes("left", l, offset, size);
+    crame_print_bytes("right", r, offset, size);
+    longjmp(crame_jmp, 1);
+}
+
+static void crame_assert_null(
+    int expect_null, const void *value, const char *file, int line,
+    const char *assertion
+) {
+    if ((value == NULL) == expect_null) {
+        putchar('.');
+        return;
+    }
+    crame_report(file, line, assertion);
+    crame_print_value("value", crame_ptr(value));
+    longjmp(crame_jmp, 1);
+}
+
+#define CRAME_ASSERT_COMPARE(op, left, right, text)                            \
+    crame_assert_compare(                                                      \
+        op, CRAME_VALUE(left), CRAME_VALUE(right), __FILE__, __LINE__, text    \
+    )
+
+#define ASSERT_EQ(left, right)                                                 \
+    CRAME_ASSERT_COMPARE(CRAME_EQ, left, right, #left " == " #right)
+#define ASSERT_NE(left, right)                                                 \
+    CRAME_ASSERT_COMPARE(CRAME_NE, left, right, #left " != " #right)
+#define ASSERT_LT(left, right)                                                 \
+    CRAME_ASSERT_COMPARE(CRAME_LT, left, right, #left " < " #right)
+#define ASSERT_LE(left, right)                                                 \
+    CRAME_ASSERT_COMPARE(CRAME_LE, left, right, #left " <= " #right)
+#define ASSERT_GT(left, right)                                                 \
+    CRAME_ASSERT_COMPARE(CRAME_GT, left, right, #left " > " #right)
+#define ASSERT_GE(left, right)                                                 \
+    CRAME_ASSERT_COMPARE(CRAME_GE, left, right, #left " >= " #right)
+
+// passes when the floats differ by at most `tolerance`
+#define ASSERT_NEAR(left, right, tolerance)                                    \
+    crame_assert_near(                                                         \
+        left, right, tolerance, __FILE__, __LINE__,                            \
+        #left " == " #right " within " #tolerance                              \
+    )
+
+#define ASSERT_STR_EQ(left, right)                                             \
+    crame_assert_str_eq(                                                       \
+        left, right, __FILE__, __LINE__, "strcmp(" #left ", " #right ") == 0"  \
+    )
+
+#define ASSERT_MEM_EQ(left, right, size)                                       \
+    crame_assert_mem_eq(                                                       \
+        left, right, size, __FILE__, __LINE__,                                 \
+        "memcmp(" #left ", " #right ", " #size ") == 0"                        \
+    )
+
+#define ASSERT_NULL(value)                                                     \
+    crame_assert_null(1, value, __FILE__, __LINE__, #value " == NULL")
+#define ASSERT_NOT_NULL(value)                                                 \
+    crame_assert_null(0, value, __FILE__, __LINE__, #value " != NULL")
+
+// after the HEADERS section of each file, hooks it does not define are null
+#define CRAME_FILE_END                                                         \
+    static crame_hook CRAME_HOOK(before_all), CRAME_HOOK(after_all),           \
+        CRAME_HOOK(before_each), CRAME_HOOK(after_each);
+
+#if defined CRAME_EDITOR
+// compile_commands.json has editors check each test file with this runner
+// included first, naming the file in CRAME_EDITOR. Its HEADERS section is
+// included here, then its TESTS section is read as functions, as it is not
+// within main.
+#undef TEST
+#define TEST(...) static void CRAME_PASTE(crame_test_, __LINE__)(void)
+#define HEADERS
+#include CRAME_EDITOR
+#undef HEADERS
+CRAME_FILE_END
 #define TESTS
-#include "./test_all.c"
+#else
+
+#define HEADERS
+#include "test_all.c"
+#undef HEADERS
+// tests included without a file id, by a hand-written test_all.c
+CRAME_FILE_END
+#undef CRAME_FILE_END
+
+// after the TESTS section of each file
+#define CRAME_FILE_END                                                         \
+    (void)CRAME_HOOK(before_all);                                              \
+    (void)CRAME_HOOK(before_each);                                             \
+    (void)CRAME_HOOK(after_each);                                              \
+    crame_file_end(CRAME_HOOK(after_all));
+
+int main(int argc, char **argv) {
+    // assertions not used by any test would otherwise warn
+    (void)crame_fail;
+    (void)crame_int;
+    (void)crame_uint;
+    (void)crame_assert_compare;
+    (void)crame_assert_near;
+    (void)crame_assert_str_eq;
+    (void)crame_assert_mem_eq;
+    (void)crame_assert_null;
+
+    if (argc == 2 && strcmp(argv[1], "--list") == 0) {
+        crame_mode = CRAME_LIST;
+        puts("# crame tests");
+    } else if (argc == 3 && strcmp(argv[1], "--run") == 0) {
+        crame_mode = CRAME_RUN_ONE;
+        crame_selected = atoi(argv[2]);
+        // keep output written before a crash
+        setvbuf(stdout, NULL, _IONBF, 0);
+    } else if (argc != 1) {
+        fprintf(stderr, "usage: %s [--list | --run <index>]\n", argv[0]);
+        return 2;
+    }
+
+    if (getenv("CRAME_TEST_EVENTS"))
+        crame_events = fopen(getenv("CRAME_TEST_EVENTS"), "a");
+
+#define TESTS
+#include "test_all.c"
+CRAME_FILE_END
 #undef TESTS
 
-    putchar('\n');
-    return 0;
+    if (crame_mode == CRAME_LIST)
+        return 0;
 
-fail:
-    printf(
-        "!\nTest failed at %s:%d\n    %s: %s\n", file, line, test, assertion
-    );
-    return -1;
-}
\ No newline at end of file
+    if (crame_mode == CRAME_RUN_ONE) {
+        if (crame_passed + crame_failed == 0) {
+            fprintf(stderr, "no test with index %d\n", crame_selected);
+            return 2;
+        }
+        return crame_failed;
+    }
+
+    printf("\n%d passed, %d failed\n", crame_passed, crame_failed);
+    return crame_failed ? 1 : 0;
+}
+
+#endif
@@ -1 +0,0 @@
-#include "unit/it_works.c"
\ No newline at end of file
@@ -3,8 +3,8 @@
 #elif defined TESTS
 
 TEST("strlen returns length of string") {
-    ASSERT(strlen("") == 0);
-    ASSERT(strlen("foo") == 3);
+    ASSERT_EQ(strlen(""), 0);
+    ASSERT_EQ(strlen("foo"), 3);
 }
 
 #endif
\ No newline at end of file